@@ -0,0 +1,294 @@
+// Building blocks of the compact binary format: LEB128 integers and an intern table so
+// every distinct string is stored once and referenced by index afterwards. The encoded
+// table and body then go through a small LZ77 pass, since names and descriptions repeat
+// words far more often than whole strings.
+
+use std::collections::HashMap;
+
+const MIN_MATCH: usize = 4;
+// Like deflate, a single match never covers more than 258 bytes, which also bounds how much
+// output every input byte can claim.
+const MAX_MATCH: usize = 258;
+const MAX_DISTANCE: usize = 1 << 16;
+const MAX_CHAIN: usize = 64;
+
+#[derive(Default)]
+pub(crate) struct Encoder<'a> {
+    strings: Vec<&'a str>,
+    indices: HashMap<&'a str, u64>,
+    body: Vec<u8>,
+}
+
+impl<'a> Encoder<'a> {
+    pub(crate) fn intern(&mut self, value: &'a str) -> u64 {
+        if let Some(&index) = self.indices.get(value) {
+            return index;
+        }
+        let index = self.strings.len() as u64;
+        self.strings.push(value);
+        self.indices.insert(value, index);
+        index
+    }
+
+    pub(crate) fn write_string(&mut self, value: &'a str) {
+        let index = self.intern(value);
+        write_varint(&mut self.body, index);
+    }
+
+    pub(crate) fn write_varint(&mut self, value: u64) {
+        write_varint(&mut self.body, value);
+    }
+
+    pub(crate) fn write_byte(&mut self, value: u8) {
+        self.body.push(value);
+    }
+
+    pub(crate) fn finish(self) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        write_varint(&mut encoded, self.strings.len() as u64);
+        for value in &self.strings {
+            write_varint(&mut encoded, value.len() as u64);
+            encoded.extend_from_slice(value.as_bytes());
+        }
+        encoded.extend_from_slice(&self.body);
+
+        let mut output = Vec::new();
+        write_varint(&mut output, encoded.len() as u64);
+        compress(&encoded, &mut output);
+        output
+    }
+}
+
+fn write_varint(output: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            output.push(byte);
+            return;
+        }
+        output.push(byte | 0x80);
+    }
+}
+
+// Tokens are a varint header whose low bit tells them apart: literal runs carry their length
+// followed by the bytes, matches carry their length and then the distance back.
+fn compress(input: &[u8], output: &mut Vec<u8>) {
+    let mut matches = MatchFinder::new(input);
+    let mut literals_start = 0;
+    let mut position = 0;
+
+    while position < input.len() {
+        let (length, distance) = matches.longest_match(position);
+        if length < MIN_MATCH {
+            matches.insert(position);
+            position += 1;
+            continue;
+        }
+
+        write_literals(output, &input[literals_start..position]);
+        write_varint(output, ((length - MIN_MATCH) as u64) << 1 | 1);
+        write_varint(output, distance as u64);
+        for offset in 0..length {
+            matches.insert(position + offset);
+        }
+        position += length;
+        literals_start = position;
+    }
+    write_literals(output, &input[literals_start..]);
+}
+
+// Hash chains over every position seen so far, keyed by the next `MIN_MATCH` bytes.
+struct MatchFinder<'a> {
+    input: &'a [u8],
+    heads: HashMap<&'a [u8], usize>,
+    previous: Vec<Option<usize>>,
+}
+
+impl<'a> MatchFinder<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        MatchFinder {
+            input,
+            heads: HashMap::new(),
+            previous: vec![None; input.len()],
+        }
+    }
+
+    fn key(&self, position: usize) -> Option<&'a [u8]> {
+        self.input.get(position..position + MIN_MATCH)
+    }
+
+    fn insert(&mut self, position: usize) {
+        if let Some(key) = self.key(position) {
+            self.previous[position] = self.heads.insert(key, position);
+        }
+    }
+
+    fn longest_match(&self, position: usize) -> (usize, usize) {
+        let mut best = (0, 0);
+        let mut candidate = self
+            .key(position)
+            .and_then(|key| self.heads.get(key).copied());
+        for _ in 0..MAX_CHAIN {
+            let start = match candidate {
+                Some(start) if position - start <= MAX_DISTANCE => start,
+                _ => break,
+            };
+            let length = self.input[start..]
+                .iter()
+                .zip(&self.input[position..])
+                .take(MAX_MATCH)
+                .take_while(|(a, b)| a == b)
+                .count();
+            if length > best.0 {
+                best = (length, position - start);
+            }
+            candidate = self.previous[start];
+        }
+        best
+    }
+}
+
+fn write_literals(output: &mut Vec<u8>, literals: &[u8]) {
+    if !literals.is_empty() {
+        write_varint(output, (literals.len() as u64) << 1);
+        output.extend_from_slice(literals);
+    }
+}
+
+fn decompress(data: &[u8]) -> Result<Vec<u8>, String> {
+    let mut reader = Reader { data, position: 0 };
+    let expected = usize::try_from(reader.read_varint()?)
+        .ok()
+        .filter(|&expected| expected <= data.len().saturating_mul(MAX_MATCH))
+        .ok_or("announced length is out of proportion to the compressed data")?;
+    let mut output: Vec<u8> = Vec::new();
+
+    while reader.position < data.len() {
+        let header = reader.read_varint()?;
+        let length = usize::try_from(header >> 1).map_err(|_| "token is too long")?;
+        if header & 1 == 0 {
+            let literals = reader
+                .position
+                .checked_add(length)
+                .and_then(|end| data.get(reader.position..end))
+                .ok_or("literal run is truncated")?;
+            output.extend_from_slice(literals);
+            reader.position += length;
+        } else {
+            let length = length
+                .checked_add(MIN_MATCH)
+                .filter(|&length| length <= MAX_MATCH)
+                .ok_or("match is too long")?;
+            let distance = reader.read_varint()? as usize;
+            if distance == 0 || distance > output.len() {
+                return Err(format!("match distance {} is out of range", distance));
+            }
+            if output.len().saturating_add(length) > expected {
+                return Err("compressed data is longer than announced".to_string());
+            }
+            // Matches may overlap the bytes they produce, so copy one byte at a time.
+            let start = output.len() - distance;
+            for offset in 0..length {
+                output.push(output[start + offset]);
+            }
+        }
+        if output.len() > expected {
+            return Err("compressed data is longer than announced".to_string());
+        }
+    }
+
+    if output.len() != expected {
+        return Err("compressed data is truncated".to_string());
+    }
+    Ok(output)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn read_varint(&mut self) -> Result<u64, String> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("integer is too long".to_string())
+    }
+
+    fn read_byte(&mut self) -> Result<u8, String> {
+        let byte = *self
+            .data
+            .get(self.position)
+            .ok_or("unexpected end of data")?;
+        self.position += 1;
+        Ok(byte)
+    }
+}
+
+pub(crate) struct Decoder {
+    data: Vec<u8>,
+    position: usize,
+    strings: Vec<String>,
+}
+
+impl Decoder {
+    pub(crate) fn new(data: &[u8]) -> Result<Self, String> {
+        let mut decoder = Decoder {
+            data: decompress(data)?,
+            position: 0,
+            strings: Vec::new(),
+        };
+        let count = decoder.read_varint()?;
+        for _ in 0..count {
+            let len = decoder.read_varint()? as usize;
+            let bytes = decoder
+                .position
+                .checked_add(len)
+                .and_then(|end| decoder.data.get(decoder.position..end))
+                .ok_or("string table is truncated")?;
+            let value = std::str::from_utf8(bytes).map_err(|_| "invalid UTF-8 in string table")?;
+            decoder.strings.push(value.to_string());
+            decoder.position += len;
+        }
+        Ok(decoder)
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.position == self.data.len()
+    }
+
+    pub(crate) fn read_string(&mut self) -> Result<&str, String> {
+        let index = self.read_varint()?;
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| self.strings.get(index))
+            .map(String::as_str)
+            .ok_or_else(|| format!("string index {} is out of range", index))
+    }
+
+    pub(crate) fn read_varint(&mut self) -> Result<u64, String> {
+        let mut reader = Reader {
+            data: &self.data,
+            position: self.position,
+        };
+        let value = reader.read_varint()?;
+        self.position = reader.position;
+        Ok(value)
+    }
+
+    pub(crate) fn read_byte(&mut self) -> Result<u8, String> {
+        let byte = *self
+            .data
+            .get(self.position)
+            .ok_or("unexpected end of data")?;
+        self.position += 1;
+        Ok(byte)
+    }
+}
@@ -0,0 +1,355 @@
+// A subset of the Graphviz DOT language: a single digraph with node statements, edge chains
+// and attribute lists. `node [...]` defaults apply to the nodes created after them, graph and
+// edge attributes are ignored. Subgraphs and ports are not supported.
+
+use std::collections::HashMap;
+
+#[derive(Debug, Default)]
+pub(crate) struct DotGraph {
+    pub(crate) nodes: Vec<DotNode>,
+    pub(crate) edges: Vec<(String, String)>,
+}
+
+#[derive(Debug)]
+pub(crate) struct DotNode {
+    pub(crate) id: String,
+    pub(crate) line: usize,
+    pub(crate) attributes: Vec<(String, String)>,
+}
+
+impl DotNode {
+    pub(crate) fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .rev()
+            .find(|(attribute, _)| attribute == key)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct DotError {
+    pub(crate) line: usize,
+    pub(crate) message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Id(String),
+    Arrow,
+    UndirectedEdge,
+    OpenBrace,
+    CloseBrace,
+    OpenBracket,
+    CloseBracket,
+    Equals,
+    Comma,
+    Semicolon,
+}
+
+pub(crate) fn parse(input: &str) -> Result<DotGraph, DotError> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        position: 0,
+        graph: DotGraph::default(),
+        node_indices: HashMap::new(),
+        node_defaults: Vec::new(),
+    };
+    parser.parse_graph()?;
+    Ok(parser.graph)
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, DotError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    let mut line = 1;
+    let mut at_line_start = true;
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '\n' => {
+                line += 1;
+                at_line_start = true;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '#' if at_line_start => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+                continue;
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+                continue;
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if previous == '*' => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            previous = c;
+                        }
+                        None => {
+                            return Err(DotError {
+                                line,
+                                message: "unterminated comment".to_string(),
+                            })
+                        }
+                    }
+                }
+                continue;
+            }
+            '{' => Token::OpenBrace,
+            '}' => Token::CloseBrace,
+            '[' => Token::OpenBracket,
+            ']' => Token::CloseBracket,
+            '=' => Token::Equals,
+            ',' => Token::Comma,
+            ';' => Token::Semicolon,
+            '-' if chars.peek() == Some(&'>') => {
+                chars.next();
+                Token::Arrow
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                chars.next();
+                Token::UndirectedEdge
+            }
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            value.push('"');
+                        }
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            value.push(c);
+                        }
+                        None => {
+                            return Err(DotError {
+                                line,
+                                message: "unterminated string".to_string(),
+                            })
+                        }
+                    }
+                }
+                Token::Id(value)
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' => {
+                let mut value = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_' || c == '.') {
+                        break;
+                    }
+                    value.push(c);
+                    chars.next();
+                }
+                Token::Id(value)
+            }
+            c => {
+                return Err(DotError {
+                    line,
+                    message: format!("unexpected character '{}'", c),
+                })
+            }
+        };
+        at_line_start = false;
+        tokens.push((token, line));
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    graph: DotGraph,
+    node_indices: HashMap<String, usize>,
+    node_defaults: Vec<(String, String)>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or_else(|| self.tokens.last())
+            .map_or(1, |(_, line)| *line)
+    }
+
+    fn error(&self, message: &str) -> DotError {
+        DotError {
+            line: self.line(),
+            message: message.to_string(),
+        }
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .map(|(token, _)| token.clone());
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token, message: &str) -> Result<(), DotError> {
+        if self.peek() == Some(&expected) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(self.error(message))
+        }
+    }
+
+    fn parse_id(&mut self) -> Result<String, DotError> {
+        match self.peek() {
+            Some(Token::Id(_)) => match self.next() {
+                Some(Token::Id(id)) => Ok(id),
+                _ => unreachable!(),
+            },
+            _ => Err(self.error("expected an identifier")),
+        }
+    }
+
+    fn parse_graph(&mut self) -> Result<(), DotError> {
+        if matches!(self.peek(), Some(Token::Id(keyword)) if keyword.eq_ignore_ascii_case("strict"))
+        {
+            self.position += 1;
+        }
+        match self.peek() {
+            Some(Token::Id(keyword)) if keyword.eq_ignore_ascii_case("digraph") => {
+                self.position += 1
+            }
+            Some(Token::Id(keyword)) if keyword.eq_ignore_ascii_case("graph") => {
+                return Err(self.error("undirected graphs are not supported, use 'digraph'"))
+            }
+            _ => return Err(self.error("expected 'digraph'")),
+        }
+        if let Some(Token::Id(_)) = self.peek() {
+            self.position += 1;
+        }
+        self.expect(Token::OpenBrace, "expected '{'")?;
+
+        loop {
+            match self.peek() {
+                Some(Token::CloseBrace) => {
+                    self.position += 1;
+                    break;
+                }
+                Some(Token::Semicolon) | Some(Token::Comma) => self.position += 1,
+                Some(_) => self.parse_statement()?,
+                None => return Err(self.error("expected '}'")),
+            }
+        }
+
+        if self.peek().is_some() {
+            return Err(self.error("unexpected content after the graph"));
+        }
+        Ok(())
+    }
+
+    fn parse_statement(&mut self) -> Result<(), DotError> {
+        let line = self.line();
+        let id = self.parse_id()?;
+        match id.as_str() {
+            "node" if self.peek() == Some(&Token::OpenBracket) => {
+                let defaults = self.parse_attributes()?;
+                self.node_defaults.extend(defaults);
+                return Ok(());
+            }
+            "graph" | "edge" if self.peek() == Some(&Token::OpenBracket) => {
+                self.parse_attributes()?;
+                return Ok(());
+            }
+            "subgraph" => return Err(self.error("subgraphs are not supported")),
+            _ => {}
+        }
+
+        match self.peek() {
+            Some(Token::Equals) => {
+                self.position += 1;
+                self.parse_id()?;
+            }
+            Some(Token::Arrow) => {
+                let mut chain = vec![id];
+                while self.peek() == Some(&Token::Arrow) {
+                    self.position += 1;
+                    chain.push(self.parse_id()?);
+                }
+                if self.peek() == Some(&Token::OpenBracket) {
+                    self.parse_attributes()?;
+                }
+                for id in &chain {
+                    self.node(id, line);
+                }
+                for pair in chain.windows(2) {
+                    self.graph.edges.push((pair[0].clone(), pair[1].clone()));
+                }
+            }
+            Some(Token::UndirectedEdge) => {
+                return Err(self.error("undirected edges are not supported, use '->'"))
+            }
+            _ => {
+                let attributes = if self.peek() == Some(&Token::OpenBracket) {
+                    self.parse_attributes()?
+                } else {
+                    Vec::new()
+                };
+                self.node(&id, line).attributes.extend(attributes);
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_attributes(&mut self) -> Result<Vec<(String, String)>, DotError> {
+        let mut attributes = Vec::new();
+        while self.peek() == Some(&Token::OpenBracket) {
+            self.position += 1;
+            loop {
+                match self.peek() {
+                    Some(Token::CloseBracket) => {
+                        self.position += 1;
+                        break;
+                    }
+                    Some(Token::Comma) | Some(Token::Semicolon) => self.position += 1,
+                    _ => {
+                        let key = self.parse_id()?;
+                        self.expect(Token::Equals, "expected '=' in attribute list")?;
+                        attributes.push((key, self.parse_id()?));
+                    }
+                }
+            }
+        }
+        Ok(attributes)
+    }
+
+    fn node(&mut self, id: &str, line: usize) -> &mut DotNode {
+        let nodes = &mut self.graph.nodes;
+        let defaults = &self.node_defaults;
+        let index = *self.node_indices.entry(id.to_string()).or_insert_with(|| {
+            nodes.push(DotNode {
+                id: id.to_string(),
+                line,
+                attributes: defaults.clone(),
+            });
+            nodes.len() - 1
+        });
+        &mut self.graph.nodes[index]
+    }
+}
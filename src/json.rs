@@ -0,0 +1,252 @@
+// Just enough JSON to read and write tech tree definitions without pulling in serde.
+
+// Deeper documents are rejected instead of risking a stack overflow in the recursive parser.
+const MAX_DEPTH: usize = 128;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object {
+        line: usize,
+        fields: Vec<(String, JsonValue)>,
+    },
+}
+
+impl JsonValue {
+    pub(crate) fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object { fields, .. } => fields
+                .iter()
+                .find(|(field, _)| field == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct JsonError {
+    pub(crate) line: usize,
+    pub(crate) message: String,
+}
+
+pub(crate) fn parse(input: &str) -> Result<JsonValue, JsonError> {
+    let mut parser = Parser {
+        input: input.as_bytes(),
+        position: 0,
+        line: 1,
+        depth: 0,
+    };
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    if parser.position < parser.input.len() {
+        return Err(parser.error("unexpected trailing characters"));
+    }
+    Ok(value)
+}
+
+pub(crate) fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    position: usize,
+    line: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> JsonError {
+        JsonError {
+            line: self.line,
+            message: message.to_string(),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(&(b' ' | b'\t' | b'\n' | b'\r')) = self.input.get(self.position) {
+            if self.input[self.position] == b'\n' {
+                self.line += 1;
+            }
+            self.position += 1;
+        }
+    }
+
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<JsonValue, JsonError>,
+    ) -> Result<JsonValue, JsonError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error("document is nested too deeply"));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), JsonError> {
+        self.skip_whitespace();
+        if self.input.get(self.position) == Some(&byte) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", byte as char)))
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<JsonValue, JsonError> {
+        self.skip_whitespace();
+        match self.input.get(self.position) {
+            Some(b'{') => self.nested(Self::parse_object),
+            Some(b'[') => self.nested(Self::parse_array),
+            Some(b'"') => self.parse_string().map(JsonValue::String),
+            Some(b't') => self.parse_literal("true", JsonValue::Bool(true)),
+            Some(b'f') => self.parse_literal("false", JsonValue::Bool(false)),
+            Some(b'n') => self.parse_literal("null", JsonValue::Null),
+            Some(b'-' | b'0'..=b'9') => self.parse_number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn parse_literal(&mut self, literal: &str, value: JsonValue) -> Result<JsonValue, JsonError> {
+        if self.input[self.position..].starts_with(literal.as_bytes()) {
+            self.position += literal.len();
+            Ok(value)
+        } else {
+            Err(self.error("invalid literal"))
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<JsonValue, JsonError> {
+        let start = self.position;
+        while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') =
+            self.input.get(self.position)
+        {
+            self.position += 1;
+        }
+        std::str::from_utf8(&self.input[start..self.position])
+            .ok()
+            .and_then(|number| number.parse::<f64>().ok())
+            .map(JsonValue::Number)
+            .ok_or_else(|| self.error("invalid number"))
+    }
+
+    fn parse_string(&mut self) -> Result<String, JsonError> {
+        self.expect(b'"')?;
+        let mut bytes = Vec::new();
+        loop {
+            match self.input.get(self.position) {
+                None => return Err(self.error("unterminated string")),
+                Some(b'"') => {
+                    self.position += 1;
+                    break;
+                }
+                Some(b'\\') => {
+                    self.position += 1;
+                    let escaped = match self.input.get(self.position) {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => {
+                            let hex = self
+                                .input
+                                .get(self.position + 1..self.position + 5)
+                                .and_then(|hex| std::str::from_utf8(hex).ok())
+                                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error("invalid unicode escape"))?;
+                            self.position += 4;
+                            hex
+                        }
+                        _ => return Err(self.error("invalid escape sequence")),
+                    };
+                    self.position += 1;
+                    let mut buffer = [0; 4];
+                    bytes.extend_from_slice(escaped.encode_utf8(&mut buffer).as_bytes());
+                }
+                Some(&byte) => {
+                    if byte == b'\n' {
+                        self.line += 1;
+                    }
+                    self.position += 1;
+                    bytes.push(byte);
+                }
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| self.error("invalid UTF-8 in string"))
+    }
+
+    fn parse_array(&mut self) -> Result<JsonValue, JsonError> {
+        self.expect(b'[')?;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.input.get(self.position) == Some(&b']') {
+            self.position += 1;
+            return Ok(JsonValue::Array(values));
+        }
+        loop {
+            values.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.input.get(self.position) {
+                Some(b',') => self.position += 1,
+                Some(b']') => {
+                    self.position += 1;
+                    return Ok(JsonValue::Array(values));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<JsonValue, JsonError> {
+        self.expect(b'{')?;
+        let line = self.line;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.input.get(self.position) == Some(&b'}') {
+            self.position += 1;
+            return Ok(JsonValue::Object { line, fields });
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.expect(b':')?;
+            fields.push((key, self.parse_value()?));
+            self.skip_whitespace();
+            match self.input.get(self.position) {
+                Some(b',') => self.position += 1,
+                Some(b'}') => {
+                    self.position += 1;
+                    return Ok(JsonValue::Object { line, fields });
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+}
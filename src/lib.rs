@@ -1,14 +1,90 @@
 use std::{
-    collections::{BinaryHeap, HashMap, HashSet},
-    fs, io,
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet, VecDeque},
+    fmt, fs, io,
+    path::Path,
+    str::FromStr,
 };
 
-#[derive(Debug, Clone)]
+use json::JsonValue;
+
+mod compact;
+mod dot;
+mod json;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Prerequisites {
     And(HashSet<String>),
     Or(HashSet<String>),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrerequisiteType {
+    And,
+    Or,
+}
+
+impl fmt::Display for PrerequisiteType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrerequisiteType::And => write!(f, "And"),
+            PrerequisiteType::Or => write!(f, "Or"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResearchStrategy {
+    CheapestFirst,
+    MostDependentsFirst,
+    HighestCostFirst,
+    ShortestPathToLeaf,
+}
+
+impl Prerequisites {
+    pub fn and_from_strs(prereqs: &[&str]) -> Self {
+        Prerequisites::And(prereqs.iter().map(|s| s.to_string()).collect())
+    }
+
+    pub fn or_from_strs(prereqs: &[&str]) -> Self {
+        Prerequisites::Or(prereqs.iter().map(|s| s.to_string()).collect())
+    }
+
+    pub fn none() -> Self {
+        Prerequisites::And(HashSet::new())
+    }
+
+    fn ids(&self) -> &HashSet<String> {
+        match self {
+            Prerequisites::And(prereqs) => prereqs,
+            Prerequisites::Or(prereqs) => prereqs,
+        }
+    }
+
+    fn ids_mut(&mut self) -> &mut HashSet<String> {
+        match self {
+            Prerequisites::And(prereqs) => prereqs,
+            Prerequisites::Or(prereqs) => prereqs,
+        }
+    }
+
+    fn kind(&self) -> PrerequisiteType {
+        match self {
+            Prerequisites::And(_) => PrerequisiteType::And,
+            Prerequisites::Or(_) => PrerequisiteType::Or,
+        }
+    }
+}
+
+impl FromStr for Prerequisites {
+    type Err = TechnologyError;
+
+    fn from_str(prereqs: &str) -> Result<Self, Self::Err> {
+        parse_prerequisites(prereqs.trim())
+            .map_err(|message| TechnologyError::Parse { line: 1, message })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Technology {
     id: String,
@@ -16,381 +92,7022 @@ pub struct Technology {
     description: String,
     prerequisites: Prerequisites,
     cost: u32,
+    era: u32,
+    tags: HashSet<String>,
+    excludes: HashSet<String>,
+    cost_multi: Option<MultiResourceCost>,
+    version: u64,
 }
 
-#[derive(Debug)]
-pub struct TechnologyTree {
-    technologies: HashMap<String, Technology>,
-}
-
-#[derive(Eq, PartialEq)]
-struct Node {
-    tech_id: String,
-    cost: i32,
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MultiResourceCost {
+    pub resources: HashMap<String, u32>,
 }
 
-impl Ord for Node {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        other.cost.cmp(&self.cost)
+impl MultiResourceCost {
+    pub fn is_affordable(&self, available_resources: &HashMap<String, u32>) -> bool {
+        self.resources.iter().all(|(resource, &amount)| {
+            available_resources.get(resource).copied().unwrap_or(0) >= amount
+        })
     }
 }
 
-impl PartialOrd for Node {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-impl TechnologyTree {
-    pub fn new() -> Self {
+impl Technology {
+    pub fn new(
+        id: String,
+        name: String,
+        description: String,
+        prerequisites: Prerequisites,
+        cost: u32,
+    ) -> Self {
         Self {
-            technologies: HashMap::new(),
+            id,
+            name,
+            description,
+            prerequisites,
+            cost,
+            era: 0,
+            tags: HashSet::new(),
+            excludes: HashSet::new(),
+            cost_multi: None,
+            version: 0,
         }
     }
 
-    pub fn add_technology(&mut self, technology: Technology) {
-        self.technologies.insert(technology.id.clone(), technology);
+    pub fn id(&self) -> &str {
+        &self.id
     }
 
-    pub fn remove_technology(&mut self, technology_id: &str) -> Result<(), String> {
-        for tech in self.technologies.values() {
-            match &tech.prerequisites {
-                Prerequisites::And(prereqs) => {
-                    if prereqs.contains(technology_id) {
-                        return Err(format!(
-                            "Technology {} is a prerequisite for {}",
-                            technology_id, tech.id
-                        ));
-                    }
-                }
-                Prerequisites::Or(prereqs) => {
-                    if prereqs.contains(technology_id) {
-                        return Err(format!(
-                            "Technology {} is a prerequisite for {}",
-                            technology_id, tech.id
-                        ));
-                    }
-                }
-            }
-        }
-        self.technologies.remove(technology_id);
-        Ok(())
+    pub fn name(&self) -> &str {
+        &self.name
     }
 
-    pub fn is_unlockable(
-        &self,
-        tech_id: &str,
-        unlocked: &HashSet<String>,
-        science_points: u32,
-    ) -> bool {
-        if let Some(tech) = self.technologies.get(tech_id) {
-            match &tech.prerequisites {
-                Prerequisites::And(prereqs) => {
-                    if prereqs.is_subset(unlocked) && tech.cost <= science_points {
-                        return true;
-                    }
-                }
-                Prerequisites::Or(prereqs) => {
-                    if prereqs.intersection(unlocked).count() > 0 && tech.cost <= science_points {
-                        return true;
-                    }
-                }
-            }
-        }
-        false
+    pub fn description(&self) -> &str {
+        &self.description
     }
 
-    pub fn unlock_technology(
-        &self,
-        tech_id: &str,
-        unlocked: &mut HashSet<String>,
-        science_points: u32,
-    ) -> bool {
-        if self.is_unlockable(tech_id, unlocked, science_points) {
-            unlocked.insert(tech_id.to_string());
-            return true;
-        }
-        false
+    pub fn prerequisites(&self) -> &Prerequisites {
+        &self.prerequisites
     }
 
-    pub fn get_unlockable_technologies(
-        &self,
-        unlocked: &HashSet<String>,
-        science_points: u32,
-    ) -> Vec<String> {
-        self.technologies
-            .keys()
-            .filter(|&tech_id| self.is_unlockable(tech_id, unlocked, science_points))
-            .cloned()
-            .collect()
+    pub fn cost(&self) -> u32 {
+        self.cost
     }
 
-    pub fn get_technology_path(
-        &self,
-        target: &str,
-        unlocked: &HashSet<String>,
-        science_points: u32,
-    ) -> Option<Vec<String>> {
-        let mut heap = BinaryHeap::new();
-        let mut parent: HashMap<String, String> = HashMap::new();
-        let mut visited: HashSet<String> = HashSet::new();
+    pub fn era(&self) -> u32 {
+        self.era
+    }
 
-        for tech in unlocked {
-            heap.push(Node {
-                tech_id: tech.clone(),
-                cost: 0,
-            });
-        }
+    pub fn tags(&self) -> &HashSet<String> {
+        &self.tags
+    }
 
-        while let Some(current) = heap.pop() {
-            let current_tech = current.tech_id;
-            let current_cost = -current.cost;
+    /// Researching any of these makes this technology unavailable for good.
+    pub fn excludes(&self) -> &HashSet<String> {
+        &self.excludes
+    }
 
-            if visited.contains(&current_tech) {
-                continue;
-            }
+    pub fn cost_multi(&self) -> Option<&MultiResourceCost> {
+        self.cost_multi.as_ref()
+    }
 
-            visited.insert(current_tech.clone());
+    pub fn version(&self) -> u64 {
+        self.version
+    }
 
-            if &current_tech == target {
-                let mut path = Vec::new();
-                let mut node = &current_tech;
-                while let Some(p) = parent.get(node) {
-                    path.push(p.clone());
-                    node = p;
-                }
-                path.reverse();
-                return Some(path);
-            }
+    pub fn format_prerequisites(&self, tree: &TechnologyTree) -> String {
+        let mut names: Vec<&str> = self
+            .prerequisites
+            .ids()
+            .iter()
+            .map(|id| {
+                tree.get_technology(id)
+                    .map_or(id.as_str(), |tech| tech.name())
+            })
+            .collect();
+        names.sort_unstable();
 
-            for (neighbor_id, neighbor) in &self.technologies {
-                if !unlocked.contains(neighbor_id)
-                    && self.is_unlockable(neighbor_id, unlocked, science_points)
-                    && !visited.contains(neighbor_id)
-                {
-                    parent.insert(neighbor_id.clone(), current_tech.clone());
-                    heap.push(Node {
-                        tech_id: neighbor_id.clone(),
-                        cost: -(current_cost as i32 + neighbor.cost as i32),
-                    });
-                }
+        match (names.as_slice(), &self.prerequisites) {
+            ([], _) => "No prerequisites".to_string(),
+            ([name], _) => format!("Requires {}", name),
+            ([rest @ .., last], Prerequisites::And(_)) => {
+                format!("Requires {} and {}", rest.join(", "), last)
+            }
+            ([first, second], Prerequisites::Or(_)) => {
+                format!("Requires any of: {} or {}", first, second)
+            }
+            ([rest @ .., last], Prerequisites::Or(_)) => {
+                format!("Requires any of: {}, or {}", rest.join(", "), last)
             }
         }
-
-        None
     }
 
-    pub fn print_tech_tree(&self, unlocked: &mut HashSet<String>, indent: usize) {
-        let roots: Vec<String> = self
-            .technologies
-            .iter()
-            .filter(|(_, tech)| match &tech.prerequisites {
-                Prerequisites::And(prereqs) => prereqs.is_empty() || prereqs.is_subset(unlocked),
-                Prerequisites::Or(prereqs) => prereqs.is_empty() || !prereqs.is_disjoint(unlocked),
-            })
-            .map(|(id, _)| id.clone())
-            .collect();
+    pub fn prerequisites_satisfied_by(&self, unlocked: &HashSet<String>) -> bool {
+        let prerequisites_met = match &self.prerequisites {
+            Prerequisites::And(prereqs) => prereqs.is_subset(unlocked),
+            Prerequisites::Or(prereqs) => prereqs.intersection(unlocked).count() > 0,
+        };
+        prerequisites_met && self.excludes.is_disjoint(unlocked)
+    }
 
-        for root in roots {
-            self.print_tech_branch(&root, unlocked, indent);
+    pub fn estimated_turns(&self, income_per_turn: u32) -> u32 {
+        if income_per_turn == 0 {
+            return u32::MAX;
         }
+        self.cost.div_ceil(income_per_turn)
     }
 
-    fn print_tech_branch(&self, tech_id: &str, unlocked: &mut HashSet<String>, indent: usize) {
-        if let Some(tech) = self.technologies.get(tech_id) {
-            println!(
-                "{}- {} (Cost: {})",
-                " ".repeat(indent),
-                tech.name,
-                tech.cost
-            );
+    pub fn with_cost(mut self, cost: u32) -> Self {
+        self.cost = cost;
+        self
+    }
 
-            unlocked.insert(tech_id.to_string());
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = name;
+        self
+    }
 
-            for (neighbor_id, _) in &self.technologies {
-                // Check if this neighbor is a child of the current technology.
-                let is_child = match &self.technologies[neighbor_id].prerequisites {
-                    Prerequisites::And(prereqs) => prereqs.contains(tech_id),
-                    Prerequisites::Or(prereqs) => prereqs.contains(tech_id),
-                };
+    pub fn with_description(mut self, description: String) -> Self {
+        self.description = description;
+        self
+    }
 
-                // If it is a child and it is unlockable, print it and go deeper.
-                if is_child && self.is_unlockable(neighbor_id, unlocked, u32::MAX) {
-                    self.print_tech_branch(neighbor_id, unlocked, indent + 4);
-                }
-            }
+    pub fn with_era(mut self, era: u32) -> Self {
+        self.era = era;
+        self
+    }
 
-            // Remove the current technology from the unlocked set before returning.
-            unlocked.remove(tech_id);
-        }
+    pub fn with_tags(mut self, tags: HashSet<String>) -> Self {
+        self.tags = tags;
+        self
     }
 
-    pub fn serialize(&self) -> String {
-        let mut serialized_data = Vec::new();
+    pub fn with_excludes(mut self, excludes: HashSet<String>) -> Self {
+        self.excludes = excludes;
+        self
+    }
 
-        for (tech_id, tech) in &self.technologies {
-            let prereqs = match &tech.prerequisites {
-                Prerequisites::And(set) => format!(
-                    "And:{}",
-                    set.iter().cloned().collect::<Vec<String>>().join(",")
-                ),
-                Prerequisites::Or(set) => format!(
-                    "Or:{}",
-                    set.iter().cloned().collect::<Vec<String>>().join(",")
-                ),
-            };
+    pub fn with_cost_multi(mut self, cost_multi: MultiResourceCost) -> Self {
+        self.cost_multi = Some(cost_multi);
+        self
+    }
+}
 
-            serialized_data.push(format!(
-                "{};{};{};{};{}",
-                tech_id, tech.name, tech.description, prereqs, tech.cost
-            ));
+#[derive(Debug)]
+pub enum TechnologyError {
+    Parse {
+        line: usize,
+        message: String,
+    },
+    Io(io::Error),
+    DuplicateId(String),
+    DanglingReference {
+        tech_id: String,
+        prerequisite: String,
+    },
+    Cycle(Vec<String>),
+    IndependentTechnology(String),
+    Disconnected(Vec<String>),
+    NotFound(String),
+    NotAPrerequisite {
+        tech_id: String,
+        prerequisite: String,
+    },
+    Unreachable(Vec<String>),
+    VersionConflict {
+        tech_id: String,
+        expected: u64,
+        found: u64,
+    },
+    PrerequisiteTypeMismatch {
+        tech_id: String,
+        expected: PrerequisiteType,
+        found: PrerequisiteType,
+    },
+    MissingId(String),
+}
+
+impl fmt::Display for TechnologyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TechnologyError::Parse { line, message } => {
+                write!(f, "Parse error on line {}: {}", line, message)
+            }
+            TechnologyError::Io(e) => write!(f, "I/O error: {}", e),
+            TechnologyError::DuplicateId(id) => write!(f, "Duplicate technology id {}", id),
+            TechnologyError::DanglingReference {
+                tech_id,
+                prerequisite,
+            } => write!(
+                f,
+                "Technology {} requires unknown technology {}",
+                tech_id, prerequisite
+            ),
+            TechnologyError::Cycle(cycle) => {
+                write!(f, "Prerequisite cycle detected: {}", cycle.join(" -> "))
+            }
+            TechnologyError::IndependentTechnology(id) => write!(
+                f,
+                "Technology {} is not connected to any other technology",
+                id
+            ),
+            TechnologyError::Disconnected(ids) => write!(
+                f,
+                "Technologies are not connected to the rest of the tree: {}",
+                ids.join(", ")
+            ),
+            TechnologyError::NotFound(id) => write!(f, "Technology {} not found", id),
+            TechnologyError::NotAPrerequisite {
+                tech_id,
+                prerequisite,
+            } => write!(
+                f,
+                "Technology {} is not a prerequisite of {}",
+                prerequisite, tech_id
+            ),
+            TechnologyError::Unreachable(ids) => {
+                write!(
+                    f,
+                    "Technologies can never be researched: {}",
+                    ids.join(", ")
+                )
+            }
+            TechnologyError::VersionConflict {
+                tech_id,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Technology {} is at version {} but the update was based on version {}",
+                tech_id, expected, found
+            ),
+            TechnologyError::PrerequisiteTypeMismatch {
+                tech_id,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Technology {} has {} prerequisites and cannot take {} replacements",
+                tech_id, expected, found
+            ),
+            TechnologyError::MissingId(name) => write!(f, "Technology {} has no id", name),
         }
+    }
+}
 
-        serialized_data.join("\n")
+impl std::error::Error for TechnologyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TechnologyError::Io(e) => Some(e),
+            _ => None,
+        }
     }
+}
 
-    pub fn deserialize(data: &str) -> Self {
-        let mut technologies = HashMap::new();
+impl From<io::Error> for TechnologyError {
+    fn from(e: io::Error) -> Self {
+        TechnologyError::Io(e)
+    }
+}
 
-        for line in data.lines() {
-            let parts: Vec<&str> = line.split(';').collect();
-            if parts.len() == 5 {
-                let (tech_id, name, description, prereqs, cost) =
-                    (parts[0], parts[1], parts[2], parts[3], parts[4]);
-                let prereq_parts: Vec<&str> = prereqs.split(':').collect();
-                let prereq_set: HashSet<String> = prereq_parts[1]
-                    .split(',')
-                    .filter(|s| !s.is_empty())
-                    .map(String::from)
-                    .collect();
+#[derive(Debug)]
+pub struct TechnologyTree {
+    technologies: HashMap<String, Technology>,
+    era_thresholds: HashMap<u32, usize>,
+    name_index: HashMap<String, String>,
+    // Technologies authored without an id, they join the tree once `assign_auto_ids` runs.
+    unnamed: Vec<Technology>,
+    version: u64,
+}
 
-                let prerequisites = match prereq_parts[0] {
-                    "And" => Prerequisites::And(prereq_set),
-                    "Or" => Prerequisites::Or(prereq_set),
-                    _ => continue,
-                };
+#[derive(Debug, Default)]
+pub struct TechTreeBuilder {
+    technologies: Vec<Technology>,
+}
 
-                let technology = Technology {
-                    id: tech_id.to_string(),
-                    name: name.to_string(),
-                    description: description.to_string(),
-                    prerequisites,
-                    cost: cost.parse::<u32>().unwrap_or(0),
-                };
-                technologies.insert(tech_id.to_string(), technology);
+// Kahn's algorithm, one technology at a time. Ready technologies come out in id order.
+struct TopologicalOrder<'a> {
+    technologies: &'a HashMap<String, Technology>,
+    dependents: HashMap<&'a str, Vec<&'a str>>,
+    pending_prereqs: HashMap<&'a str, usize>,
+    ready: BinaryHeap<Reverse<&'a str>>,
+}
+
+impl<'a> Iterator for TopologicalOrder<'a> {
+    type Item = &'a Technology;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse(tech_id) = self.ready.pop()?;
+        for &dependent in self.dependents.get(tech_id).into_iter().flatten() {
+            if let Some(count) = self.pending_prereqs.get_mut(dependent) {
+                *count -= 1;
+                if *count == 0 {
+                    self.pending_prereqs.remove(dependent);
+                    self.ready.push(Reverse(dependent));
+                }
             }
         }
-
-        TechnologyTree { technologies }
+        self.technologies.get(tech_id)
     }
+}
 
-    pub fn load_from_file(filename: &str) -> io::Result<Self> {
-        let data = fs::read_to_string(filename)?;
-        let tech_tree = TechnologyTree::deserialize(&data);
-        Ok(tech_tree)
+impl Default for TechnologyTree {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl TechnologyTree {
+    pub fn new() -> Self {
+        Self {
+            technologies: HashMap::new(),
+            era_thresholds: HashMap::new(),
+            name_index: HashMap::new(),
+            unnamed: Vec::new(),
+            version: 0,
+        }
+    }
 
-    #[test]
-    fn test_add_technology() {
-        let mut tech_tree = TechnologyTree::new();
-        let tech = Technology {
-            id: "pottery".to_string(),
-            name: "Pottery".to_string(),
-            description: "Basic pottery techniques.".to_string(),
-            prerequisites: Prerequisites::And(HashSet::new()),
-            cost: 5,
-        };
+    /// In debug builds this panics if the new technology closes a prerequisite cycle.
+    pub fn add_technology(&mut self, technology: Technology) {
+        let tech_id = technology.id.clone();
+        self.insert_technology(technology);
+        debug_assert!(
+            self.find_cycle_from(&tech_id, &mut Vec::new(), &mut HashSet::new())
+                .is_none(),
+            "adding {} created a prerequisite cycle",
+            tech_id
+        );
+    }
 
-        tech_tree.add_technology(tech.clone());
+    fn insert_technology(&mut self, technology: Technology) {
+        self.load_technology(technology);
+        self.version += 1;
+    }
 
-        assert!(tech_tree.technologies.contains_key(&tech.id));
+    fn load_technology(&mut self, technology: Technology) {
+        if technology.id.is_empty() {
+            self.unnamed.push(technology);
+        } else {
+            self.technologies.insert(technology.id.clone(), technology);
+        }
     }
 
-    #[test]
-    fn test_is_unlockable() {
-        let mut tech_tree = TechnologyTree::new();
-        let mut unlocked = HashSet::new();
-        unlocked.insert("pottery".to_string());
+    pub fn add_technology_after(
+        &mut self,
+        new_tech: Technology,
+        after_id: &str,
+        prereq_type: PrerequisiteType,
+    ) -> Result<(), TechnologyError> {
+        if new_tech.id.is_empty() {
+            return Err(TechnologyError::MissingId(new_tech.name));
+        }
+        if !self.technologies.contains_key(after_id) {
+            return Err(TechnologyError::NotFound(after_id.to_string()));
+        }
+        if self.technologies.contains_key(&new_tech.id) {
+            return Err(TechnologyError::DuplicateId(new_tech.id));
+        }
 
-        let tech = Technology {
-            id: "writing".to_string(),
-            name: "Writing".to_string(),
-            description: "Basics of writing.".to_string(),
-            prerequisites: Prerequisites::And(unlocked.clone()),
-            cost: 10,
+        let prereqs = HashSet::from([after_id.to_string()]);
+        let prerequisites = match prereq_type {
+            PrerequisiteType::And => Prerequisites::And(prereqs),
+            PrerequisiteType::Or => Prerequisites::Or(prereqs),
         };
-
-        tech_tree.add_technology(tech.clone());
-
-        assert!(tech_tree.is_unlockable(&tech.id, &unlocked, 15));
+        self.add_technology(Technology {
+            prerequisites,
+            ..new_tech
+        });
+        Ok(())
+    }
+
+    pub fn add_technology_between(
+        &mut self,
+        new_tech: Technology,
+        parent_id: &str,
+        child_id: &str,
+    ) -> Result<(), TechnologyError> {
+        if new_tech.id.is_empty() {
+            return Err(TechnologyError::MissingId(new_tech.name));
+        }
+        if !self.technologies.contains_key(parent_id) {
+            return Err(TechnologyError::NotFound(parent_id.to_string()));
+        }
+        let child = self
+            .technologies
+            .get(child_id)
+            .ok_or_else(|| TechnologyError::NotFound(child_id.to_string()))?;
+        if !child.prerequisites.ids().contains(parent_id) {
+            return Err(TechnologyError::NotAPrerequisite {
+                tech_id: child_id.to_string(),
+                prerequisite: parent_id.to_string(),
+            });
+        }
+        if self.technologies.contains_key(&new_tech.id) {
+            return Err(TechnologyError::DuplicateId(new_tech.id));
+        }
+
+        let new_id = new_tech.id.clone();
+        self.insert_technology(Technology {
+            prerequisites: Prerequisites::And(HashSet::from([parent_id.to_string()])),
+            ..new_tech
+        });
+        let child = self.technologies.get_mut(child_id).unwrap();
+        let prereqs = child.prerequisites.ids_mut();
+        prereqs.remove(parent_id);
+        prereqs.insert(new_id);
+        child.version += 1;
+        Ok(())
+    }
+
+    pub fn update_technology(&mut self, updated: Technology) -> Result<(), TechnologyError> {
+        let current = self
+            .technologies
+            .get_mut(&updated.id)
+            .ok_or_else(|| TechnologyError::NotFound(updated.id.clone()))?;
+        if updated.version != current.version {
+            return Err(TechnologyError::VersionConflict {
+                tech_id: updated.id,
+                expected: current.version,
+                found: updated.version,
+            });
+        }
+
+        let version = current.version + 1;
+        *current = Technology { version, ..updated };
+        self.version += 1;
+        Ok(())
+    }
+
+    pub fn get_technology_version(&self, technology_id: &str) -> Option<u64> {
+        self.technologies
+            .get(technology_id)
+            .map(|tech| tech.version)
+    }
+
+    /// Incremented whenever technologies are added, removed or edited through the tree.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn assign_auto_ids(&mut self) {
+        let mut unnamed = std::mem::take(&mut self.unnamed);
+        if unnamed.is_empty() {
+            return;
+        }
+        // Stable sort, so technologies sharing a name keep the order they were added in.
+        unnamed.sort_by(|a, b| a.name.cmp(&b.name));
+
+        // Collect every old reference first so the rewrite below doesn't depend on map order.
+        // Technologies authored by name reference each other by name, unless the name is also
+        // the ID of an existing technology.
+        let mut new_ids: HashMap<String, String> = HashMap::new();
+        for mut tech in unnamed {
+            let base_id = snake_case(&tech.name);
+            let mut id = base_id.clone();
+            let mut suffix = 2;
+            while self.technologies.contains_key(&id) {
+                id = format!("{}_{}", base_id, suffix);
+                suffix += 1;
+            }
+
+            if !self.technologies.contains_key(&tech.name) {
+                new_ids
+                    .entry(tech.name.clone())
+                    .or_insert_with(|| id.clone());
+            }
+            tech.id = id;
+            self.insert_technology(tech);
+        }
+
+        for tech in self.technologies.values_mut() {
+            let prereqs = tech.prerequisites.ids_mut();
+            if !prereqs.iter().any(|prereq| new_ids.contains_key(prereq)) {
+                continue;
+            }
+            *prereqs = prereqs
+                .drain()
+                .map(|prereq| new_ids.get(&prereq).cloned().unwrap_or(prereq))
+                .collect();
+            tech.version += 1;
+        }
+        self.build_name_index();
+    }
+
+    pub fn get_technology(&self, technology_id: &str) -> Option<&Technology> {
+        self.technologies.get(technology_id)
+    }
+
+    /// Counts technologies still waiting for `assign_auto_ids` too.
+    pub fn len(&self) -> usize {
+        self.technologies.len() + self.unnamed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.technologies.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.technologies
+            .values()
+            .map(|tech| tech.prerequisites.ids().len())
+            .sum()
+    }
+
+    pub fn validate(&self) -> Result<(), TechnologyError> {
+        let mut technologies: Vec<&Technology> = self.technologies.values().collect();
+        technologies.sort_by(|a, b| a.id.cmp(&b.id));
+        for tech in technologies {
+            let mut prereqs: Vec<&String> = tech.prerequisites.ids().iter().collect();
+            prereqs.sort();
+            if let Some(prereq) = prereqs
+                .into_iter()
+                .find(|prereq| !self.technologies.contains_key(*prereq))
+            {
+                return Err(TechnologyError::DanglingReference {
+                    tech_id: tech.id.clone(),
+                    prerequisite: prereq.clone(),
+                });
+            }
+        }
+
+        self.assert_no_cycles()?;
+
+        // A lone technology is a valid tree, otherwise unconnected ones are most likely typos.
+        if self.technologies.len() > 1 {
+            if let Some(tech) = self.get_independent_technologies().first() {
+                return Err(TechnologyError::IndependentTechnology(tech.id.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn assert_no_cycles(&self) -> Result<(), TechnologyError> {
+        match self.find_cycle() {
+            Some(cycle) => Err(TechnologyError::Cycle(cycle)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn topological_sort(&self) -> Result<Vec<&Technology>, TechnologyError> {
+        Ok(self.iter_in_topological_order()?.collect())
+    }
+
+    pub fn iter_in_topological_order(
+        &self,
+    ) -> Result<impl Iterator<Item = &Technology>, TechnologyError> {
+        self.assert_no_cycles()?;
+
+        let dependents = self.dependents_map();
+        let mut pending_prereqs: HashMap<&str, usize> = HashMap::new();
+        let mut ready = BinaryHeap::new();
+        for tech in self.technologies.values() {
+            let count = tech
+                .prerequisites
+                .ids()
+                .iter()
+                .filter(|prereq| self.technologies.contains_key(*prereq))
+                .count();
+            if count == 0 {
+                ready.push(Reverse(tech.id.as_str()));
+            } else {
+                pending_prereqs.insert(tech.id.as_str(), count);
+            }
+        }
+
+        Ok(TopologicalOrder {
+            technologies: &self.technologies,
+            dependents,
+            pending_prereqs,
+            ready,
+        })
+    }
+
+    pub fn get_independent_technologies(&self) -> Vec<&Technology> {
+        let dependents = self.dependents_map();
+        let mut independent: Vec<&Technology> = self
+            .technologies
+            .values()
+            .filter(|tech| {
+                tech.prerequisites.ids().is_empty() && !dependents.contains_key(tech.id.as_str())
+            })
+            .collect();
+        independent.sort_by(|a, b| a.id.cmp(&b.id));
+        independent
+    }
+
+    fn find_cycle(&self) -> Option<Vec<String>> {
+        let mut ids: Vec<&str> = self.technologies.keys().map(String::as_str).collect();
+        ids.sort_unstable();
+
+        let mut finished: HashSet<&str> = HashSet::new();
+        for id in ids {
+            let mut stack: Vec<&str> = Vec::new();
+            if let Some(cycle) = self.find_cycle_from(id, &mut stack, &mut finished) {
+                return Some(cycle);
+            }
+        }
+        None
+    }
+
+    fn find_cycle_from<'a>(
+        &'a self,
+        tech_id: &'a str,
+        stack: &mut Vec<&'a str>,
+        finished: &mut HashSet<&'a str>,
+    ) -> Option<Vec<String>> {
+        if finished.contains(tech_id) {
+            return None;
+        }
+        if let Some(start) = stack.iter().position(|&id| id == tech_id) {
+            let mut cycle: Vec<String> = stack[start..].iter().map(|id| id.to_string()).collect();
+            cycle.push(tech_id.to_string());
+            return Some(cycle);
+        }
+
+        stack.push(tech_id);
+        if let Some(tech) = self.technologies.get(tech_id) {
+            let mut prereqs: Vec<&String> = tech.prerequisites.ids().iter().collect();
+            prereqs.sort();
+            for prereq in prereqs {
+                if let Some(cycle) = self.find_cycle_from(prereq, stack, finished) {
+                    return Some(cycle);
+                }
+            }
+        }
+        stack.pop();
+        finished.insert(tech_id);
+        None
+    }
+
+    pub fn get_technologies_with_cost_in_range(
+        &self,
+        min_cost: u32,
+        max_cost: u32,
+    ) -> Vec<&Technology> {
+        self.technologies_sorted_by_cost(|tech| (min_cost..=max_cost).contains(&tech.cost))
+    }
+
+    pub fn get_technologies_with_cost_below(&self, cost: u32) -> Vec<&Technology> {
+        self.technologies_sorted_by_cost(|tech| tech.cost < cost)
+    }
+
+    pub fn get_technologies_with_cost_above(&self, cost: u32) -> Vec<&Technology> {
+        self.technologies_sorted_by_cost(|tech| tech.cost > cost)
+    }
+
+    fn technologies_sorted_by_cost<F>(&self, predicate: F) -> Vec<&Technology>
+    where
+        F: Fn(&Technology) -> bool,
+    {
+        let mut technologies: Vec<&Technology> = self
+            .technologies
+            .values()
+            .filter(|tech| predicate(tech))
+            .collect();
+        technologies.sort_by(|a, b| a.cost.cmp(&b.cost).then_with(|| a.id.cmp(&b.id)));
+        technologies
+    }
+
+    pub fn get_research_milestones(&self) -> [Option<&Technology>; 4] {
+        let technologies = self.technologies_sorted_by_cost(|_| true);
+        let mut cumulative_cost = 0u64;
+        let running_sums: Vec<(u64, &Technology)> = technologies
+            .into_iter()
+            .map(|tech| {
+                cumulative_cost += u64::from(tech.cost);
+                (cumulative_cost, tech)
+            })
+            .collect();
+
+        [1, 2, 3, 4].map(|quarter| {
+            let milestone = cumulative_cost * quarter / 4;
+            running_sums
+                .iter()
+                .min_by_key(|(sum, _)| sum.abs_diff(milestone))
+                .map(|(_, tech)| *tech)
+        })
+    }
+
+    pub fn get_cost_percentile(&self, cost: u32) -> f64 {
+        if self.technologies.is_empty() {
+            return 0.0;
+        }
+        let at_or_below = self
+            .technologies
+            .values()
+            .filter(|tech| tech.cost <= cost)
+            .count();
+        at_or_below as f64 / self.technologies.len() as f64
+    }
+
+    pub fn get_cost_at_percentile(&self, percentile: f64) -> u32 {
+        let mut costs: Vec<u32> = self.technologies.values().map(|tech| tech.cost).collect();
+        if costs.is_empty() {
+            return 0;
+        }
+        costs.sort_unstable();
+
+        let rank = (percentile.clamp(0.0, 1.0) * costs.len() as f64).ceil() as usize;
+        costs[rank.clamp(1, costs.len()) - 1]
+    }
+
+    pub fn get_cost_histogram(&self, bucket_size: u32) -> Vec<(u32, usize)> {
+        let bucket_size = bucket_size.max(1);
+        let mut buckets: HashMap<u32, usize> = HashMap::new();
+        for tech in self.technologies.values() {
+            *buckets
+                .entry(tech.cost - tech.cost % bucket_size)
+                .or_default() += 1;
+        }
+
+        let mut histogram: Vec<(u32, usize)> = buckets.into_iter().collect();
+        histogram.sort_unstable();
+        histogram
+    }
+
+    pub fn set_technology_cost_by_predicate<F: Fn(&Technology) -> bool>(
+        &mut self,
+        predicate: F,
+        new_cost: u32,
+    ) {
+        let mut changed = false;
+        for tech in self.technologies.values_mut() {
+            if predicate(tech) {
+                tech.cost = new_cost;
+                tech.version += 1;
+                changed = true;
+            }
+        }
+        if changed {
+            self.version += 1;
+        }
+    }
+
+    /// Sets every cost to `base_cost * multiplier^depth`, rounded, as a starting point
+    /// for balancing a draft tree.
+    pub fn auto_assign_costs(&mut self, base_cost: u32, multiplier: f32) {
+        let mut depths = HashMap::new();
+        let costs: Vec<(String, u32)> = self
+            .technologies
+            .keys()
+            .map(|tech_id| {
+                let depth = self.depth(tech_id, &mut depths, &mut HashSet::new());
+                let cost = (base_cost as f32 * multiplier.powi(depth as i32)).round() as u32;
+                (tech_id.clone(), cost)
+            })
+            .collect();
+
+        let mut changed = false;
+        for (tech_id, cost) in costs {
+            let tech = self.technologies.get_mut(&tech_id).unwrap();
+            if tech.cost != cost {
+                tech.cost = cost;
+                tech.version += 1;
+                changed = true;
+            }
+        }
+        if changed {
+            self.version += 1;
+        }
+    }
+
+    pub fn normalize_costs(&mut self, target_total: u32) {
+        let current_total: u64 = self
+            .technologies
+            .values()
+            .map(|tech| u64::from(tech.cost))
+            .sum();
+        if current_total == 0 {
+            return;
+        }
+
+        let target_total = u64::from(target_total);
+        let mut costs: Vec<(&str, u64)> = self
+            .technologies
+            .values()
+            .map(|tech| {
+                let scaled =
+                    (u64::from(tech.cost) * target_total * 2 + current_total) / (current_total * 2);
+                (tech.id.as_str(), scaled)
+            })
+            .collect();
+        let new_total: u64 = costs.iter().map(|(_, cost)| cost).sum();
+
+        let highest = costs
+            .iter_mut()
+            .max_by(|(a_id, a), (b_id, b)| a.cmp(b).then_with(|| b_id.cmp(a_id)));
+        if let Some((_, cost)) = highest {
+            *cost = (*cost + target_total).saturating_sub(new_total);
+        }
+
+        let costs: Vec<(String, u32)> = costs
+            .into_iter()
+            .map(|(tech_id, cost)| (tech_id.to_string(), cost as u32))
+            .collect();
+        let mut changed = false;
+        for (tech_id, cost) in costs {
+            let tech = self.technologies.get_mut(&tech_id).unwrap();
+            if tech.cost != cost {
+                tech.cost = cost;
+                tech.version += 1;
+                changed = true;
+            }
+        }
+        if changed {
+            self.version += 1;
+        }
+    }
+
+    pub fn get_technologies_by_prerequisite_count(&self) -> Vec<(&Technology, usize)> {
+        let mut technologies: Vec<(&Technology, usize)> = self
+            .technologies
+            .values()
+            .map(|tech| (tech, tech.prerequisites.ids().len()))
+            .collect();
+        technologies.sort_by(|(a, a_count), (b, b_count)| {
+            a_count.cmp(b_count).then_with(|| a.id.cmp(&b.id))
+        });
+        technologies
+    }
+
+    pub fn get_direct_dependents(&self, tech_id: &str) -> Vec<&Technology> {
+        let mut dependents: Vec<&Technology> = self
+            .technologies
+            .values()
+            .filter(|tech| tech.prerequisites.ids().contains(tech_id))
+            .collect();
+        dependents.sort_by(|a, b| a.id.cmp(&b.id));
+        dependents
+    }
+
+    pub fn get_related_technologies(&self, tech_id: &str) -> Vec<&Technology> {
+        let prereqs = match self.technologies.get(tech_id) {
+            Some(tech) => tech.prerequisites.ids(),
+            None => return Vec::new(),
+        };
+        let mut related: Vec<&Technology> = self
+            .technologies
+            .values()
+            .filter(|tech| tech.id != tech_id && !tech.prerequisites.ids().is_disjoint(prereqs))
+            .collect();
+        related.sort_by(|a, b| a.id.cmp(&b.id));
+        related
+    }
+
+    pub fn get_max_fan_out(&self) -> Option<(&Technology, usize)> {
+        let dependents = self.dependents_map();
+        self.technologies
+            .values()
+            .map(|tech| (tech, dependents.get(tech.id.as_str()).map_or(0, Vec::len)))
+            .max_by(|(a, a_fan_out), (b, b_fan_out)| {
+                a_fan_out.cmp(b_fan_out).then_with(|| b.id.cmp(&a.id))
+            })
+    }
+
+    pub fn get_avg_fan_out(&self) -> f64 {
+        if self.technologies.is_empty() {
+            return 0.0;
+        }
+        let links: usize = self.dependents_map().values().map(Vec::len).sum();
+        links as f64 / self.technologies.len() as f64
+    }
+
+    pub fn get_max_fan_in(&self) -> Option<(&Technology, usize)> {
+        self.technologies
+            .values()
+            .map(|tech| (tech, tech.prerequisites.ids().len()))
+            .max_by(|(a, a_fan_in), (b, b_fan_in)| {
+                a_fan_in.cmp(b_fan_in).then_with(|| b.id.cmp(&a.id))
+            })
+    }
+
+    pub fn get_avg_fan_in(&self) -> f64 {
+        if self.technologies.is_empty() {
+            return 0.0;
+        }
+        self.edge_count() as f64 / self.technologies.len() as f64
+    }
+
+    /// Averages five components in [0, 1] and scales the result to [0, 100], with `n` the
+    /// number of technologies:
+    ///
+    /// - chains: `1 - (longest_chain - 1) / n`, long linear runs feel grindy
+    /// - parallelism: `1 - waves / n`, with waves from `group_by_wave`
+    /// - orphans: `1 - independent / n`, a lone technology is not an orphan
+    /// - costs: `1 - min(1, std_dev / mean)`, an even cost distribution scores best
+    /// - fan: `1 - max(max_fan_in, max_fan_out) / (n - 1)`, no single hub or convergence point
+    ///
+    /// An empty tree scores 0.
+    pub fn get_balance_score(&self) -> f64 {
+        let n = self.technologies.len();
+        if n == 0 {
+            return 0.0;
+        }
+        let total = n as f64;
+
+        let chains = 1.0 - (self.get_longest_chain_length() - 1) as f64 / total;
+        let parallelism = 1.0 - self.group_by_wave().len() as f64 / total;
+        let orphans = if n > 1 {
+            1.0 - self.get_independent_technologies().len() as f64 / total
+        } else {
+            1.0
+        };
+
+        let costs: Vec<f64> = self
+            .technologies
+            .values()
+            .map(|tech| tech.cost as f64)
+            .collect();
+        let mean = costs.iter().sum::<f64>() / total;
+        let costs = if mean > 0.0 {
+            let variance = costs.iter().map(|cost| (cost - mean).powi(2)).sum::<f64>() / total;
+            1.0 - (variance.sqrt() / mean).min(1.0)
+        } else {
+            1.0
+        };
+
+        let fan = if n > 1 {
+            let max_fan_in = self.get_max_fan_in().map_or(0, |(_, fan_in)| fan_in);
+            let max_fan_out = self.get_max_fan_out().map_or(0, |(_, fan_out)| fan_out);
+            1.0 - (max_fan_in.max(max_fan_out) as f64 / (total - 1.0)).min(1.0)
+        } else {
+            1.0
+        };
+
+        (chains + parallelism + orphans + costs + fan) / 5.0 * 100.0
+    }
+
+    pub fn get_cheapest_root_technology(&self, science_points: u32) -> Option<&Technology> {
+        self.affordable_roots(science_points)
+            .min_by(|a, b| a.cost.cmp(&b.cost).then_with(|| a.id.cmp(&b.id)))
+    }
+
+    pub fn get_most_impactful_root_technology(&self, science_points: u32) -> Option<&Technology> {
+        self.affordable_roots(science_points)
+            .map(|tech| (tech, self.get_direct_dependents(&tech.id).len()))
+            .max_by(|(a, a_dependents), (b, b_dependents)| {
+                a_dependents.cmp(b_dependents).then_with(|| b.id.cmp(&a.id))
+            })
+            .map(|(tech, _)| tech)
+    }
+
+    fn affordable_roots(&self, science_points: u32) -> impl Iterator<Item = &Technology> {
+        let nothing_unlocked = HashSet::new();
+        self.technologies.values().filter(move |tech| {
+            tech.prerequisites.ids().is_empty()
+                && self.is_unlockable(&tech.id, &nothing_unlocked, science_points)
+        })
+    }
+
+    pub fn get_technology_count_by_tag(&self) -> HashMap<&str, usize> {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for tech in self.technologies.values() {
+            for tag in &tech.tags {
+                *counts.entry(tag.as_str()).or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    pub fn get_most_common_tag(&self) -> Option<&str> {
+        self.get_technology_count_by_tag()
+            .into_iter()
+            .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then_with(|| b.cmp(a)))
+            .map(|(tag, _)| tag)
+    }
+
+    pub fn get_technology_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .technologies
+            .values()
+            .map(|tech| tech.name.as_str())
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    pub fn get_technology_ids(&self) -> Vec<&str> {
+        self.get_tech_ids_in_stable_order()
+    }
+
+    pub fn get_tech_ids_in_stable_order(&self) -> Vec<&str> {
+        let mut ids: Vec<&str> = self.technologies.keys().map(String::as_str).collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// The index is a snapshot, call this again after adding, removing or renaming technologies.
+    pub fn build_name_index(&mut self) {
+        let mut technologies: Vec<&Technology> = self.technologies.values().collect();
+        // Sort descending so the alphabetically first ID wins for duplicate names.
+        technologies.sort_by(|a, b| b.id.cmp(&a.id));
+        self.name_index = technologies
+            .into_iter()
+            .map(|tech| (tech.name.clone(), tech.id.clone()))
+            .collect();
+    }
+
+    pub fn get_tech_id_for_name(&self, name: &str) -> Option<&str> {
+        self.name_index.get(name).map(String::as_str)
+    }
+
+    pub fn remove_technology(&mut self, technology_id: &str) -> Result<(), String> {
+        for tech in self.technologies.values() {
+            match &tech.prerequisites {
+                Prerequisites::And(prereqs) => {
+                    if prereqs.contains(technology_id) {
+                        return Err(format!(
+                            "Technology {} is a prerequisite for {}",
+                            technology_id, tech.id
+                        ));
+                    }
+                }
+                Prerequisites::Or(prereqs) => {
+                    if prereqs.contains(technology_id) {
+                        return Err(format!(
+                            "Technology {} is a prerequisite for {}",
+                            technology_id, tech.id
+                        ));
+                    }
+                }
+            }
+        }
+        self.technologies.remove(technology_id);
+        self.version += 1;
+        Ok(())
+    }
+
+    /// Dependents that only required `tech_id` take over `replacement_prereqs` as they are,
+    /// others get the replacement ids merged in, which needs both to be of the same type
+    /// unless the replacement is a single id.
+    pub fn remove_technology_replace_prerequisites(
+        &mut self,
+        tech_id: &str,
+        replacement_prereqs: Prerequisites,
+    ) -> Result<(), TechnologyError> {
+        if !self.technologies.contains_key(tech_id) {
+            return Err(TechnologyError::NotFound(tech_id.to_string()));
+        }
+
+        let mut replacement_prereqs = replacement_prereqs;
+        replacement_prereqs.ids_mut().remove(tech_id);
+        let mut replacement_ids: Vec<&str> = replacement_prereqs
+            .ids()
+            .iter()
+            .map(String::as_str)
+            .collect();
+        replacement_ids.sort_unstable();
+        if let Some(missing) = replacement_ids
+            .iter()
+            .find(|id| !self.technologies.contains_key(**id))
+        {
+            return Err(TechnologyError::NotFound(missing.to_string()));
+        }
+
+        let mut dependents = self.get_direct_dependents(tech_id);
+        dependents.retain(|tech| tech.id != tech_id);
+        for tech in &dependents {
+            let kind = tech.prerequisites.kind();
+            if tech.prerequisites.ids().len() > 1
+                && replacement_ids.len() > 1
+                && kind != replacement_prereqs.kind()
+            {
+                return Err(TechnologyError::PrerequisiteTypeMismatch {
+                    tech_id: tech.id.clone(),
+                    expected: kind,
+                    found: replacement_prereqs.kind(),
+                });
+            }
+        }
+
+        // Dependents get the replacement ids as prerequisites, so a dependent that a
+        // replacement still requires without going through `tech_id` would require itself.
+        let dependent_ids: HashSet<&str> = dependents.iter().map(|tech| tech.id.as_str()).collect();
+        let mut seen = HashSet::from([tech_id]);
+        for &replacement in &replacement_ids {
+            let mut path = Vec::new();
+            if self.find_path_to(replacement, &dependent_ids, &mut path, &mut seen) {
+                let mut cycle: Vec<String> = path.iter().rev().map(|id| id.to_string()).collect();
+                cycle.insert(0, cycle[cycle.len() - 1].clone());
+                return Err(TechnologyError::Cycle(cycle));
+            }
+        }
+
+        self.technologies.remove(tech_id);
+        for tech in self.technologies.values_mut() {
+            let prereqs = tech.prerequisites.ids_mut();
+            if !prereqs.remove(tech_id) {
+                continue;
+            }
+            if prereqs.is_empty() {
+                tech.prerequisites = replacement_prereqs.clone();
+            } else {
+                prereqs.extend(replacement_prereqs.ids().iter().cloned());
+            }
+            tech.version += 1;
+        }
+
+        self.version += 1;
+        Ok(())
+    }
+
+    // Depth-first through prerequisites, leaving `path` running from `targets` back to
+    // `tech_id` when one of them is reachable.
+    fn find_path_to<'a>(
+        &'a self,
+        tech_id: &'a str,
+        targets: &HashSet<&str>,
+        path: &mut Vec<&'a str>,
+        seen: &mut HashSet<&'a str>,
+    ) -> bool {
+        if !seen.insert(tech_id) {
+            return false;
+        }
+        if targets.contains(tech_id) {
+            path.push(tech_id);
+            return true;
+        }
+        if let Some(tech) = self.technologies.get(tech_id) {
+            let mut prereqs: Vec<&String> = tech.prerequisites.ids().iter().collect();
+            prereqs.sort();
+            for prereq in prereqs {
+                if self.find_path_to(prereq, targets, path, seen) {
+                    path.push(tech_id);
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    pub fn is_unlockable(
+        &self,
+        tech_id: &str,
+        unlocked: &HashSet<String>,
+        science_points: u32,
+    ) -> bool {
+        match self.technologies.get(tech_id) {
+            Some(tech) => self.prerequisites_met(tech, unlocked) && tech.cost <= science_points,
+            None => false,
+        }
+    }
+
+    /// Technologies without a multi-resource cost fall back to their `cost` paid in `"science"`.
+    pub fn is_unlockable_multi(
+        &self,
+        tech_id: &str,
+        unlocked: &HashSet<String>,
+        available_resources: &HashMap<String, u32>,
+    ) -> bool {
+        let tech = match self.technologies.get(tech_id) {
+            Some(tech) => tech,
+            None => return false,
+        };
+        let affordable = match &tech.cost_multi {
+            Some(cost) => cost.is_affordable(available_resources),
+            None => available_resources.get("science").copied().unwrap_or(0) >= tech.cost,
+        };
+        affordable && self.prerequisites_met(tech, unlocked)
+    }
+
+    fn prerequisites_met(&self, tech: &Technology, unlocked: &HashSet<String>) -> bool {
+        self.is_era_open(tech.era, unlocked) && tech.prerequisites_satisfied_by(unlocked)
+    }
+
+    pub fn set_era_threshold(&mut self, era: u32, required_in_previous_era: usize) {
+        self.era_thresholds.insert(era, required_in_previous_era);
+    }
+
+    pub fn get_technologies_in_era_order(&self) -> Vec<&Technology> {
+        let mut technologies: Vec<&Technology> = self.technologies.values().collect();
+        technologies.sort_by(|a, b| {
+            a.era
+                .cmp(&b.era)
+                .then_with(|| a.name.cmp(&b.name))
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        technologies
+    }
+
+    /// Returns `(era, start, end)` ranges into `get_technologies_in_era_order`, `end` is exclusive.
+    pub fn get_era_boundaries(&self) -> Vec<(u32, usize, usize)> {
+        let mut boundaries: Vec<(u32, usize, usize)> = Vec::new();
+        for (index, tech) in self.get_technologies_in_era_order().iter().enumerate() {
+            match boundaries.last_mut() {
+                Some((era, _, end)) if *era == tech.era => *end = index + 1,
+                _ => boundaries.push((tech.era, index, index + 1)),
+            }
+        }
+        boundaries
+    }
+
+    fn is_era_open(&self, era: u32, unlocked: &HashSet<String>) -> bool {
+        let required = match self.era_thresholds.get(&era) {
+            Some(&required) if era > 0 => required,
+            _ => return true,
+        };
+        let unlocked_in_previous_era = unlocked
+            .iter()
+            .filter_map(|tech_id| self.technologies.get(tech_id))
+            .filter(|tech| tech.era == era - 1)
+            .count();
+        unlocked_in_previous_era >= required
+    }
+
+    pub fn unlock_technology(
+        &self,
+        tech_id: &str,
+        unlocked: &mut HashSet<String>,
+        science_points: u32,
+    ) -> bool {
+        if self.is_unlockable(tech_id, unlocked, science_points) {
+            unlocked.insert(tech_id.to_string());
+            return true;
+        }
+        false
+    }
+
+    pub fn get_unlockable_technologies(
+        &self,
+        unlocked: &HashSet<String>,
+        science_points: u32,
+    ) -> Vec<String> {
+        self.iter_unlockable(unlocked, science_points)
+            .map(|tech| tech.id.clone())
+            .collect()
+    }
+
+    pub fn iter_unlockable<'a>(
+        &'a self,
+        unlocked: &'a HashSet<String>,
+        science_points: u32,
+    ) -> impl Iterator<Item = &'a Technology> {
+        self.technologies
+            .values()
+            .filter(move |tech| self.is_unlockable(&tech.id, unlocked, science_points))
+    }
+
+    pub fn get_research_priority_queue(
+        &self,
+        unlocked: &HashSet<String>,
+        science_points: u32,
+        strategy: ResearchStrategy,
+    ) -> Vec<&Technology> {
+        let mut queue: Vec<&Technology> = self
+            .technologies
+            .values()
+            .filter(|tech| {
+                !unlocked.contains(&tech.id)
+                    && self.is_unlockable(&tech.id, unlocked, science_points)
+            })
+            .collect();
+        queue.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let dependents = self.dependents_map();
+        match strategy {
+            ResearchStrategy::CheapestFirst => queue.sort_by_key(|tech| tech.cost),
+            ResearchStrategy::HighestCostFirst => queue.sort_by_key(|tech| Reverse(tech.cost)),
+            ResearchStrategy::MostDependentsFirst => queue
+                .sort_by_key(|tech| Reverse(dependents.get(tech.id.as_str()).map_or(0, Vec::len))),
+            ResearchStrategy::ShortestPathToLeaf => {
+                let mut hops_to_leaf: HashMap<&str, usize> = HashMap::new();
+                for tech in &queue {
+                    let mut distances = HashMap::from([(tech.id.as_str(), 0)]);
+                    let mut bfs = VecDeque::from([tech.id.as_str()]);
+                    while let Some(current) = bfs.pop_front() {
+                        let children = dependents.get(current).map_or(&[][..], Vec::as_slice);
+                        if children.is_empty() {
+                            hops_to_leaf.insert(tech.id.as_str(), distances[current]);
+                            break;
+                        }
+                        for &child in children {
+                            if !distances.contains_key(child) {
+                                distances.insert(child, distances[current] + 1);
+                                bfs.push_back(child);
+                            }
+                        }
+                    }
+                }
+                queue.sort_by_key(|tech| {
+                    hops_to_leaf
+                        .get(tech.id.as_str())
+                        .copied()
+                        .unwrap_or(usize::MAX)
+                });
+            }
+        }
+        queue
+    }
+
+    pub fn get_newly_unlockable_after_unlock(
+        &self,
+        newly_unlocked: &str,
+        previous_unlocked: &HashSet<String>,
+        science_points: u32,
+    ) -> Vec<&Technology> {
+        let mut unlocked = previous_unlocked.clone();
+        unlocked.insert(newly_unlocked.to_string());
+
+        // Only dependents can change state, unless the unlock also opens up a new era.
+        let candidates: Vec<&Technology> = if self.era_thresholds.is_empty() {
+            self.get_direct_dependents(newly_unlocked)
+        } else {
+            self.technologies.values().collect()
+        };
+        let mut newly_unlockable: Vec<&Technology> = candidates
+            .into_iter()
+            .filter(|tech| {
+                !unlocked.contains(&tech.id)
+                    && self.is_unlockable(&tech.id, &unlocked, science_points)
+                    && !self.is_unlockable(&tech.id, previous_unlocked, science_points)
+            })
+            .collect();
+        newly_unlockable.sort_by(|a, b| a.id.cmp(&b.id));
+        newly_unlockable
+    }
+
+    /// Prerequisites and era thresholds only ever open up as more gets researched, so only
+    /// technologies excluding `newly_unlocked` can get locked.
+    pub fn get_technologies_locked_by_unlock(
+        &self,
+        newly_unlocked: &str,
+        previous_unlocked: &HashSet<String>,
+    ) -> Vec<&Technology> {
+        let mut unlocked = previous_unlocked.clone();
+        unlocked.insert(newly_unlocked.to_string());
+
+        let mut locked: Vec<&Technology> = self
+            .technologies
+            .values()
+            .filter(|tech| {
+                !unlocked.contains(&tech.id)
+                    && self.prerequisites_met(tech, previous_unlocked)
+                    && !self.prerequisites_met(tech, &unlocked)
+            })
+            .collect();
+        locked.sort_by(|a, b| a.id.cmp(&b.id));
+        locked
+    }
+
+    pub fn get_parallel_research_sets(
+        &self,
+        unlocked: &HashSet<String>,
+        science_points: u32,
+    ) -> Vec<HashSet<String>> {
+        let mut candidates: Vec<String> = self
+            .get_unlockable_technologies(unlocked, science_points)
+            .into_iter()
+            .filter(|tech_id| !unlocked.contains(tech_id))
+            .collect();
+        candidates.sort();
+
+        let mut sets: Vec<HashSet<String>> = Vec::new();
+        for tech_id in candidates {
+            let ancestors = self.get_all_ancestors(&tech_id);
+            let independent_set = sets.iter_mut().find(|set| {
+                set.iter().all(|other| {
+                    !ancestors.contains(other) && !self.get_all_ancestors(other).contains(&tech_id)
+                })
+            });
+
+            match independent_set {
+                Some(set) => {
+                    set.insert(tech_id);
+                }
+                None => sets.push(HashSet::from([tech_id])),
+            }
+        }
+
+        sets
+    }
+
+    pub fn get_mandatory_predecessors(
+        &self,
+        target: &str,
+        unlocked: &HashSet<String>,
+    ) -> HashSet<String> {
+        let mut memo = HashMap::new();
+        let mut visiting = HashSet::new();
+        self.mandatory_predecessors(target, unlocked, &mut memo, &mut visiting)
+    }
+
+    /// Everything that has to be researched before `tech_id` regardless of which Or
+    /// alternatives are picked, only cycles reachable from `tech_id` are reported.
+    pub fn get_transitive_and_prerequisites(
+        &self,
+        tech_id: &str,
+    ) -> Result<HashSet<String>, TechnologyError> {
+        let (tech_id, _) = self
+            .technologies
+            .get_key_value(tech_id)
+            .ok_or_else(|| TechnologyError::NotFound(tech_id.to_string()))?;
+        if let Some(cycle) = self.find_cycle_from(tech_id, &mut Vec::new(), &mut HashSet::new()) {
+            return Err(TechnologyError::Cycle(cycle));
+        }
+        Ok(self.get_mandatory_predecessors(tech_id, &HashSet::new()))
+    }
+
+    /// Every minimal set of technologies whose research makes `tech_id` unlockable, smallest
+    /// sets first. The number of sets can grow exponentially with the number of Or branches.
+    pub fn get_transitive_or_prerequisites(&self, tech_id: &str) -> Vec<HashSet<String>> {
+        let tech = match self.technologies.get(tech_id) {
+            Some(tech) => tech,
+            None => return Vec::new(),
+        };
+        let mut memo = HashMap::new();
+        let mut visiting = HashSet::from([tech_id.to_string()]);
+        let mut sets = self.prerequisite_options(tech, &mut memo, &mut visiting);
+        sets.sort_by_cached_key(|set| {
+            let mut ids: Vec<String> = set.iter().cloned().collect();
+            ids.sort_unstable();
+            (ids.len(), ids)
+        });
+        sets
+    }
+
+    fn prerequisite_options(
+        &self,
+        tech: &Technology,
+        memo: &mut HashMap<String, Vec<HashSet<String>>>,
+        visiting: &mut HashSet<String>,
+    ) -> Vec<HashSet<String>> {
+        let mut prereqs: Vec<&String> = tech.prerequisites.ids().iter().collect();
+        prereqs.sort();
+        let branches: Vec<Vec<HashSet<String>>> = prereqs
+            .into_iter()
+            .map(|prereq| self.research_options(prereq, memo, visiting))
+            .collect();
+
+        // And needs one option from every branch, Or is happy with any option of any branch.
+        let options = match &tech.prerequisites {
+            Prerequisites::And(_) => {
+                branches
+                    .into_iter()
+                    .fold(vec![HashSet::new()], |acc, branch| {
+                        acc.iter()
+                            .flat_map(|set| {
+                                branch
+                                    .iter()
+                                    .map(move |option| set.union(option).cloned().collect())
+                            })
+                            .collect()
+                    })
+            }
+            Prerequisites::Or(_) => branches.into_iter().flatten().collect(),
+        };
+        minimal_sets(options)
+    }
+
+    fn research_options(
+        &self,
+        tech_id: &str,
+        memo: &mut HashMap<String, Vec<HashSet<String>>>,
+        visiting: &mut HashSet<String>,
+    ) -> Vec<HashSet<String>> {
+        if let Some(options) = memo.get(tech_id) {
+            return options.clone();
+        }
+        let tech = match self.technologies.get(tech_id) {
+            Some(tech) => tech,
+            None => return Vec::new(),
+        };
+        // A cyclic branch can never be researched, so it offers no options.
+        if !visiting.insert(tech_id.to_string()) {
+            return Vec::new();
+        }
+
+        let mut options = self.prerequisite_options(tech, memo, visiting);
+        for option in &mut options {
+            option.insert(tech_id.to_string());
+        }
+
+        visiting.remove(tech_id);
+        memo.insert(tech_id.to_string(), options.clone());
+        options
+    }
+
+    fn mandatory_predecessors(
+        &self,
+        tech_id: &str,
+        unlocked: &HashSet<String>,
+        memo: &mut HashMap<String, HashSet<String>>,
+        visiting: &mut HashSet<String>,
+    ) -> HashSet<String> {
+        if let Some(mandatory) = memo.get(tech_id) {
+            return mandatory.clone();
+        }
+        let tech = match self.technologies.get(tech_id) {
+            Some(tech) => tech,
+            None => return HashSet::new(),
+        };
+        // Guard against cycles, a cyclic branch can't be part of a valid research sequence.
+        if !visiting.insert(tech_id.to_string()) {
+            return HashSet::new();
+        }
+
+        let mut branches = Vec::new();
+        for prereq in tech.prerequisites.ids() {
+            let mut branch = HashSet::new();
+            if !unlocked.contains(prereq) {
+                branch = self.mandatory_predecessors(prereq, unlocked, memo, visiting);
+                branch.insert(prereq.clone());
+            }
+            branches.push(branch);
+        }
+
+        // Every And prerequisite has to be researched, while for Or only the
+        // technologies shared by all alternatives are unavoidable.
+        let mandatory: HashSet<String> = match &tech.prerequisites {
+            Prerequisites::And(_) => branches.into_iter().flatten().collect(),
+            Prerequisites::Or(_) => branches
+                .into_iter()
+                .reduce(|a, b| a.intersection(&b).cloned().collect())
+                .unwrap_or_default(),
+        };
+
+        visiting.remove(tech_id);
+        memo.insert(tech_id.to_string(), mandatory.clone());
+        mandatory
+    }
+
+    pub fn get_prerequisite_edges(&self) -> Vec<(String, String, PrerequisiteType)> {
+        let mut edges: Vec<(String, String, PrerequisiteType)> = self
+            .technologies
+            .values()
+            .flat_map(|tech| {
+                tech.prerequisites
+                    .ids()
+                    .iter()
+                    .map(|prereq| (prereq.clone(), tech.id.clone(), tech.prerequisites.kind()))
+            })
+            .collect();
+        edges.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+        edges
+    }
+
+    /// IDs are sorted alphabetically, `matrix[i][j]` is `true` if `ids[i]` is a prerequisite of `ids[j]`.
+    pub fn get_adjacency_matrix(&self) -> (Vec<String>, Vec<Vec<bool>>) {
+        let ids: Vec<String> = self
+            .get_technology_ids()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let index: HashMap<&str, usize> = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (id.as_str(), i))
+            .collect();
+
+        let mut matrix = vec![vec![false; ids.len()]; ids.len()];
+        for (j, id) in ids.iter().enumerate() {
+            for prereq in self.technologies[id].prerequisites.ids() {
+                if let Some(&i) = index.get(prereq.as_str()) {
+                    matrix[i][j] = true;
+                }
+            }
+        }
+
+        (ids, matrix)
+    }
+
+    pub fn get_average_path_length(&self) -> f64 {
+        let dependents = self.dependents_map();
+        let mut distances: HashMap<&str, usize> = HashMap::new();
+        let mut queue = VecDeque::new();
+
+        for tech in self.technologies.values() {
+            if tech.prerequisites.ids().is_empty() {
+                distances.insert(tech.id.as_str(), 0);
+                queue.push_back(tech.id.as_str());
+            }
+        }
+
+        while let Some(current) = queue.pop_front() {
+            let distance = distances[current];
+            for &dependent in dependents.get(current).into_iter().flatten() {
+                if !distances.contains_key(dependent) {
+                    distances.insert(dependent, distance + 1);
+                    queue.push_back(dependent);
+                }
+            }
+        }
+
+        let leaf_distances: Vec<usize> = self
+            .technologies
+            .keys()
+            .filter(|tech_id| !dependents.contains_key(tech_id.as_str()))
+            .filter_map(|tech_id| distances.get(tech_id.as_str()).copied())
+            .collect();
+
+        if leaf_distances.is_empty() {
+            return 0.0;
+        }
+        leaf_distances.iter().sum::<usize>() as f64 / leaf_distances.len() as f64
+    }
+
+    pub fn get_technology_influence_score(&self, tech_id: &str) -> f64 {
+        if !self.technologies.contains_key(tech_id) {
+            return 0.0;
+        }
+
+        let dependents = self.dependents_map();
+        let through = shortest_path_counts(tech_id, &dependents);
+
+        // Hop-based betweenness: for every pair (source, target) sum the fraction of
+        // shortest research paths from source to target that pass through `tech_id`.
+        let mut score = 0.0;
+        for source in self.technologies.keys() {
+            if source == tech_id {
+                continue;
+            }
+            let from_source = shortest_path_counts(source, &dependents);
+            let (to_tech_distance, to_tech_paths) = match from_source.get(tech_id) {
+                Some(&counts) => counts,
+                None => continue,
+            };
+
+            for (target, &(distance, paths)) in &from_source {
+                if *target == tech_id || *target == source.as_str() {
+                    continue;
+                }
+                if let Some(&(from_tech_distance, from_tech_paths)) = through.get(target) {
+                    if to_tech_distance + from_tech_distance == distance {
+                        score += to_tech_paths * from_tech_paths / paths;
+                    }
+                }
+            }
+        }
+
+        score
+    }
+
+    pub fn get_connected_components(&self) -> Vec<HashSet<String>> {
+        let dependents = self.dependents_map();
+        let mut ids: Vec<&str> = self.technologies.keys().map(String::as_str).collect();
+        ids.sort_unstable();
+
+        let mut visited: HashSet<&str> = HashSet::new();
+        let mut components: Vec<HashSet<String>> = Vec::new();
+        for id in ids {
+            if !visited.insert(id) {
+                continue;
+            }
+            let mut component = HashSet::new();
+            let mut queue = VecDeque::from([id]);
+            while let Some(current) = queue.pop_front() {
+                component.insert(current.to_string());
+                let prereqs = self.technologies[current]
+                    .prerequisites
+                    .ids()
+                    .iter()
+                    .map(String::as_str)
+                    .filter(|prereq| self.technologies.contains_key(*prereq));
+                let children = dependents.get(current).into_iter().flatten().copied();
+                for neighbor in prereqs.chain(children) {
+                    if visited.insert(neighbor) {
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+            components.push(component);
+        }
+
+        // Components were found in id order, a stable sort keeps that for equal sizes.
+        components.sort_by_key(|component| Reverse(component.len()));
+        components
+    }
+
+    pub fn invert_prerequisites(&self) -> TechnologyTree {
+        let dependents = self.dependents_map();
+        let technologies = self
+            .technologies
+            .values()
+            .map(|tech| {
+                let prereqs = dependents
+                    .get(tech.id.as_str())
+                    .into_iter()
+                    .flatten()
+                    .map(|dependent| dependent.to_string())
+                    .collect();
+                let inverted = Technology {
+                    prerequisites: Prerequisites::And(prereqs),
+                    ..tech.clone()
+                };
+                (tech.id.clone(), inverted)
+            })
+            .collect();
+
+        TechnologyTree {
+            technologies,
+            ..Default::default()
+        }
+    }
+
+    pub fn get_diameter(&self) -> usize {
+        let mut neighbors: HashMap<&str, Vec<&str>> = HashMap::new();
+        for tech in self.technologies.values() {
+            for prereq in tech.prerequisites.ids() {
+                if let Some((prereq, _)) = self.technologies.get_key_value(prereq) {
+                    neighbors.entry(prereq).or_default().push(&tech.id);
+                    neighbors.entry(&tech.id).or_default().push(prereq);
+                }
+            }
+        }
+
+        let mut diameter = 0;
+        for start in self.technologies.keys() {
+            let mut distances = HashMap::from([(start.as_str(), 0)]);
+            let mut queue = VecDeque::from([start.as_str()]);
+            while let Some(current) = queue.pop_front() {
+                let distance = distances[current];
+                diameter = diameter.max(distance);
+                for &neighbor in neighbors.get(current).into_iter().flatten() {
+                    if !distances.contains_key(neighbor) {
+                        distances.insert(neighbor, distance + 1);
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+        }
+        diameter
+    }
+
+    pub fn get_sibling_groups(&self) -> Vec<Vec<String>> {
+        fn find(group: &mut [usize], i: usize) -> usize {
+            if group[i] != i {
+                group[i] = find(group, group[i]);
+            }
+            group[i]
+        }
+
+        let mut ids: Vec<&str> = self.technologies.keys().map(String::as_str).collect();
+        ids.sort_unstable();
+        let index: HashMap<&str, usize> = ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+
+        // Union-find over technologies, siblings are merged through their shared parents.
+        let mut group: Vec<usize> = (0..ids.len()).collect();
+
+        let mut first_child: HashMap<&str, usize> = HashMap::new();
+        let mut first_root: Option<usize> = None;
+        for (i, id) in ids.iter().enumerate() {
+            let prereqs = self.technologies[*id].prerequisites.ids();
+            let parents: Vec<&str> = prereqs
+                .iter()
+                .map(String::as_str)
+                .filter(|prereq| index.contains_key(prereq))
+                .collect();
+
+            let mut representatives: Vec<usize> = Vec::new();
+            if parents.is_empty() {
+                representatives.push(*first_root.get_or_insert(i));
+            }
+            for parent in parents {
+                representatives.push(*first_child.entry(parent).or_insert(i));
+            }
+            for representative in representatives {
+                let (a, b) = (find(&mut group, representative), find(&mut group, i));
+                group[a.max(b)] = a.min(b);
+            }
+        }
+
+        let mut groups: Vec<Vec<String>> = Vec::new();
+        let mut group_index: HashMap<usize, usize> = HashMap::new();
+        for (i, id) in ids.iter().enumerate() {
+            let root = find(&mut group, i);
+            let position = *group_index.entry(root).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[position].push(id.to_string());
+        }
+        groups
+    }
+
+    fn dependents_map(&self) -> HashMap<&str, Vec<&str>> {
+        let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+        for tech in self.technologies.values() {
+            for prereq in tech.prerequisites.ids() {
+                if self.technologies.contains_key(prereq) {
+                    dependents
+                        .entry(prereq.as_str())
+                        .or_default()
+                        .push(tech.id.as_str());
+                }
+            }
+        }
+        dependents
+    }
+
+    pub fn get_all_ancestors(&self, tech_id: &str) -> HashSet<String> {
+        let mut ancestors = HashSet::new();
+        let mut stack = vec![tech_id.to_string()];
+
+        while let Some(current) = stack.pop() {
+            if let Some(tech) = self.technologies.get(&current) {
+                for prereq in tech.prerequisites.ids() {
+                    if ancestors.insert(prereq.clone()) {
+                        stack.push(prereq.clone());
+                    }
+                }
+            }
+        }
+
+        ancestors
+    }
+
+    /// The targets themselves are left out, even when one target is a prerequisite of another.
+    pub fn get_prerequisite_closure_for_set(&self, targets: &HashSet<String>) -> HashSet<String> {
+        targets
+            .iter()
+            .flat_map(|target| self.get_all_ancestors(target))
+            .filter(|tech_id| !targets.contains(tech_id))
+            .collect()
+    }
+
+    pub fn get_all_descendants(&self, tech_id: &str) -> HashSet<String> {
+        let dependents = self.dependents_map();
+        let mut descendants = HashSet::new();
+        let mut stack = vec![tech_id];
+
+        while let Some(current) = stack.pop() {
+            for &dependent in dependents.get(current).into_iter().flatten() {
+                if dependent != tech_id && descendants.insert(dependent.to_string()) {
+                    stack.push(dependent);
+                }
+            }
+        }
+
+        descendants
+    }
+
+    pub fn get_impact_score(&self, tech_id: &str) -> usize {
+        self.get_all_descendants(tech_id).len()
+    }
+
+    pub fn get_subtree_cost(&self, root_id: &str) -> u32 {
+        let root = match self.technologies.get(root_id) {
+            Some(root) => root,
+            None => return 0,
+        };
+        self.get_all_descendants(root_id)
+            .iter()
+            .filter_map(|tech_id| self.technologies.get(tech_id))
+            .fold(root.cost, |total, tech| total.saturating_add(tech.cost))
+    }
+
+    pub fn get_technology_chain(&self, start_id: &str) -> Vec<String> {
+        if !self.technologies.contains_key(start_id) {
+            return Vec::new();
+        }
+        let dependents = self.dependents_map();
+        let only_prerequisite = |tech_id: &str| -> Option<&str> {
+            let prereqs: Vec<&String> = self.technologies[tech_id]
+                .prerequisites
+                .ids()
+                .iter()
+                .filter(|prereq| self.technologies.contains_key(*prereq))
+                .collect();
+            match prereqs.as_slice() {
+                [prereq] => Some(prereq.as_str()),
+                _ => None,
+            }
+        };
+        let only_dependent = |tech_id: &str| match dependents.get(tech_id).map(Vec::as_slice) {
+            Some([dependent]) => Some(*dependent),
+            _ => None,
+        };
+
+        let mut chain = VecDeque::from([start_id]);
+        let mut visited = HashSet::from([start_id]);
+        while let Some(prereq) = only_prerequisite(chain[0]) {
+            if only_dependent(prereq) != Some(chain[0]) || !visited.insert(prereq) {
+                break;
+            }
+            chain.push_front(prereq);
+        }
+        while let Some(dependent) = only_dependent(chain[chain.len() - 1]) {
+            if only_prerequisite(dependent) != Some(chain[chain.len() - 1])
+                || !visited.insert(dependent)
+            {
+                break;
+            }
+            chain.push_back(dependent);
+        }
+
+        chain.into_iter().map(String::from).collect()
+    }
+
+    pub fn get_longest_chain_length(&self) -> usize {
+        let mut ids: Vec<&String> = self.technologies.keys().collect();
+        ids.sort();
+
+        let mut visited: HashSet<String> = HashSet::new();
+        let mut longest = 0;
+        for id in ids {
+            if visited.contains(id) {
+                continue;
+            }
+            let chain = self.get_technology_chain(id);
+            longest = longest.max(chain.len());
+            visited.extend(chain);
+        }
+        longest
+    }
+
+    pub fn find_common_prerequisites(&self, tech_id_a: &str, tech_id_b: &str) -> HashSet<String> {
+        let ancestors_a = self.get_all_ancestors(tech_id_a);
+        let ancestors_b = self.get_all_ancestors(tech_id_b);
+        ancestors_a.intersection(&ancestors_b).cloned().collect()
+    }
+
+    pub fn get_technology_path(
+        &self,
+        target: &str,
+        unlocked: &HashSet<String>,
+        science_points: u32,
+    ) -> Option<Vec<String>> {
+        self.find_technology_path(target, unlocked, science_points, |_| true)
+    }
+
+    pub fn get_recommended_technology<'a>(
+        &'a self,
+        unlocked: &HashSet<String>,
+        science_points: u32,
+        goal: Option<&str>,
+    ) -> Option<&'a Technology> {
+        if let Some(goal) = goal {
+            let path = self.get_technology_path(goal, unlocked, science_points)?;
+            return path.first().and_then(|id| self.technologies.get(id));
+        }
+
+        self.technologies
+            .values()
+            .filter(|tech| {
+                !unlocked.contains(&tech.id)
+                    && self.is_unlockable(&tech.id, unlocked, science_points)
+            })
+            .max_by(|a, b| {
+                self.get_impact_score(&a.id)
+                    .cmp(&self.get_impact_score(&b.id))
+                    .then_with(|| b.id.cmp(&a.id))
+            })
+    }
+
+    pub fn get_technologies_in_prerequisite_order(
+        &self,
+        target: &str,
+        unlocked: &HashSet<String>,
+    ) -> Option<Vec<&Technology>> {
+        self.get_research_order_for_target(target, unlocked, u32::MAX)
+    }
+
+    pub fn get_research_order_for_target(
+        &self,
+        target: &str,
+        unlocked: &HashSet<String>,
+        science_points: u32,
+    ) -> Option<Vec<&Technology>> {
+        let path = self.get_technology_path(target, unlocked, science_points)?;
+        Some(
+            path.iter()
+                .map(|tech_id| &self.technologies[tech_id])
+                .collect(),
+        )
+    }
+
+    pub fn get_research_path_narrative(
+        &self,
+        target: &str,
+        unlocked: &HashSet<String>,
+        science_points: u32,
+    ) -> Option<String> {
+        let order = self.get_research_order_for_target(target, unlocked, science_points)?;
+        let step = |tech: &Technology| format!("{} ({} pts)", tech.name, tech.cost);
+
+        let (target_tech, earlier) = match order.split_last() {
+            Some(split) => split,
+            None => {
+                let name = self
+                    .technologies
+                    .get(target)
+                    .map_or(target, |tech| tech.name.as_str());
+                return Some(format!("{} has already been researched.", name));
+            }
+        };
+        let total: u64 = order.iter().map(|tech| u64::from(tech.cost)).sum();
+
+        let steps = match earlier {
+            [] => format!("research {}", step(target_tech)),
+            [first, rest @ ..] => {
+                let mut steps = format!("you must first research {}", step(first));
+                for tech in rest {
+                    steps.push_str(&format!(", then {}", step(tech)));
+                }
+                steps.push_str(&format!(", and finally {}", step(target_tech)));
+                steps
+            }
+        };
+        Some(format!(
+            "To unlock {}, {} for a total of {} science points.",
+            target_tech.name, steps, total
+        ))
+    }
+
+    pub fn get_path_to_multiple_targets(
+        &self,
+        targets: &[&str],
+        unlocked: &HashSet<String>,
+        science_points: u32,
+    ) -> Option<Vec<String>> {
+        let mut researched = unlocked.clone();
+        let mut remaining: Vec<&str> = targets.to_vec();
+        let mut sequence = Vec::new();
+
+        // Greedily walk to whichever remaining target is currently cheapest to reach, reusing
+        // everything researched for the earlier targets.
+        while !remaining.is_empty() {
+            let mut nearest: Option<(usize, u64, Vec<String>)> = None;
+            for (index, target) in remaining.iter().enumerate() {
+                let path = self.get_technology_path(target, &researched, science_points)?;
+                let cost: u64 = path
+                    .iter()
+                    .map(|tech_id| u64::from(self.technologies[tech_id].cost))
+                    .sum();
+                if nearest.as_ref().is_none_or(|(_, best, _)| cost < *best) {
+                    nearest = Some((index, cost, path));
+                }
+            }
+
+            let (index, _, path) = nearest?;
+            remaining.remove(index);
+            researched.extend(path.iter().cloned());
+            sequence.extend(path);
+        }
+
+        Some(sequence)
+    }
+
+    pub fn get_technology_path_excluding(
+        &self,
+        target: &str,
+        unlocked: &HashSet<String>,
+        science_points: u32,
+        excluded: &HashSet<String>,
+    ) -> Option<Vec<String>> {
+        self.find_technology_path(target, unlocked, science_points, |tech| {
+            !excluded.contains(&tech.id)
+        })
+    }
+
+    pub fn find_alternative_path(
+        &self,
+        target: &str,
+        blocked: &str,
+        unlocked: &HashSet<String>,
+        points: u32,
+    ) -> Option<Vec<String>> {
+        let excluded = HashSet::from([blocked.to_string()]);
+        self.get_technology_path_excluding(target, unlocked, points, &excluded)
+    }
+
+    pub fn get_cost_profile(&self, sequence: &[String]) -> Vec<(usize, u32)> {
+        let mut cumulative_cost = 0u32;
+        sequence
+            .iter()
+            .enumerate()
+            .map(|(index, tech_id)| {
+                let cost = self.technologies.get(tech_id).map_or(0, |tech| tech.cost);
+                cumulative_cost = cumulative_cost.saturating_add(cost);
+                (index + 1, cumulative_cost)
+            })
+            .collect()
+    }
+
+    /// Paths too expensive to count in a `u32` report `u32::MAX` turns.
+    pub fn compute_turns_to_target(
+        &self,
+        target: &str,
+        unlocked: &HashSet<String>,
+        current_points: u32,
+        income_per_turn: u32,
+    ) -> Option<u32> {
+        if unlocked.contains(target) {
+            return Some(0);
+        }
+
+        let path = self.get_technology_path(target, unlocked, u32::MAX)?;
+        let total_cost: u64 = path
+            .iter()
+            .filter_map(|tech_id| self.technologies.get(tech_id))
+            .map(|tech| u64::from(tech.cost))
+            .sum();
+
+        let missing = total_cost.saturating_sub(u64::from(current_points));
+        if missing == 0 {
+            Some(0)
+        } else if income_per_turn == 0 {
+            None
+        } else {
+            let turns = missing.div_ceil(u64::from(income_per_turn));
+            Some(u32::try_from(turns).unwrap_or(u32::MAX))
+        }
+    }
+
+    pub fn simulate_n_steps(
+        &self,
+        steps: usize,
+        unlocked: &HashSet<String>,
+        science_points: u32,
+        income_per_step: u32,
+    ) -> (HashSet<String>, u32) {
+        let mut unlocked = unlocked.clone();
+        let mut points = science_points;
+
+        for _ in 0..steps {
+            points = points.saturating_add(income_per_step);
+            let cheapest = self
+                .technologies
+                .values()
+                .filter(|tech| {
+                    !unlocked.contains(&tech.id) && self.is_unlockable(&tech.id, &unlocked, points)
+                })
+                .min_by(|a, b| a.cost.cmp(&b.cost).then_with(|| a.id.cmp(&b.id)));
+
+            if let Some(tech) = cheapest {
+                points -= tech.cost;
+                unlocked.insert(tech.id.clone());
+            }
+        }
+
+        (unlocked, points)
+    }
+
+    /// Stops at technologies that couldn't be researched before turn `u32::MAX`.
+    pub fn simulate_research_schedule(
+        &self,
+        unlocked: HashSet<String>,
+        income_per_turn: u32,
+    ) -> Vec<(u32, String)> {
+        let mut unlocked = unlocked;
+        let mut points: u64 = 0;
+        let mut turn: u64 = 0;
+        let mut schedule = Vec::new();
+        let income = u64::from(income_per_turn);
+
+        loop {
+            let cheapest_available = self
+                .technologies
+                .values()
+                .filter(|tech| {
+                    !unlocked.contains(&tech.id) && self.prerequisites_met(tech, &unlocked)
+                })
+                .map(|tech| u64::from(tech.cost))
+                .min();
+            // Skip the turns spent saving up for the cheapest technology.
+            let turns = match cheapest_available {
+                Some(cost) if cost <= points => 1,
+                Some(cost) if income > 0 => (cost - points).div_ceil(income),
+                _ => break,
+            };
+            turn += turns;
+            let turn = match u32::try_from(turn) {
+                Ok(turn) => turn,
+                Err(_) => break,
+            };
+            points += turns * income;
+
+            while let Some(tech) = self
+                .technologies
+                .values()
+                .filter(|tech| {
+                    !unlocked.contains(&tech.id)
+                        && u64::from(tech.cost) <= points
+                        && self.prerequisites_met(tech, &unlocked)
+                })
+                .min_by(|a, b| a.cost.cmp(&b.cost).then_with(|| a.id.cmp(&b.id)))
+            {
+                points -= u64::from(tech.cost);
+                unlocked.insert(tech.id.clone());
+                schedule.push((turn, tech.id.clone()));
+            }
+        }
+
+        schedule
+    }
+
+    pub fn get_full_unlock_sequence(
+        &self,
+        income_per_turn: u32,
+    ) -> Result<Vec<String>, TechnologyError> {
+        self.validate()?;
+        // `validate` only catches lone technologies, not separate islands of several.
+        let mut components = self.get_connected_components();
+        if components.len() > 1 {
+            let mut disconnected: Vec<String> = components.drain(1..).flatten().collect();
+            disconnected.sort();
+            return Err(TechnologyError::Disconnected(disconnected));
+        }
+
+        let dependents = self.dependents_map();
+        let mut unlocked = HashSet::new();
+        let mut sequence = Vec::with_capacity(self.technologies.len());
+        let mut points: u64 = 0;
+
+        while sequence.len() < self.technologies.len() {
+            let available: Vec<&Technology> = self
+                .technologies
+                .values()
+                .filter(|tech| {
+                    !unlocked.contains(&tech.id) && self.prerequisites_met(tech, &unlocked)
+                })
+                .collect();
+
+            let cheapest = available.iter().map(|tech| u64::from(tech.cost)).min();
+            let cheapest = match cheapest {
+                Some(cost) if cost <= points || income_per_turn > 0 => cost,
+                _ => {
+                    let mut remaining: Vec<String> = self
+                        .technologies
+                        .keys()
+                        .filter(|id| !unlocked.contains(*id))
+                        .cloned()
+                        .collect();
+                    remaining.sort();
+                    return Err(TechnologyError::Unreachable(remaining));
+                }
+            };
+            if cheapest > points {
+                let turns = (cheapest - points).div_ceil(u64::from(income_per_turn));
+                points += turns * u64::from(income_per_turn);
+            }
+
+            let most_impactful = available
+                .into_iter()
+                .filter(|tech| u64::from(tech.cost) <= points)
+                .max_by(|a, b| {
+                    let impact =
+                        |tech: &Technology| dependents.get(tech.id.as_str()).map_or(0, Vec::len);
+                    impact(a)
+                        .cmp(&impact(b))
+                        .then_with(|| b.cost.cmp(&a.cost))
+                        .then_with(|| b.id.cmp(&a.id))
+                })
+                .expect("the cheapest available technology is affordable");
+
+            points -= u64::from(most_impactful.cost);
+            unlocked.insert(most_impactful.id.clone());
+            sequence.push(most_impactful.id.clone());
+        }
+
+        Ok(sequence)
+    }
+
+    pub fn get_completion_percentage(&self, unlocked: &HashSet<String>) -> f64 {
+        if self.technologies.is_empty() {
+            return 0.0;
+        }
+        let researched = self
+            .technologies
+            .keys()
+            .filter(|tech_id| unlocked.contains(*tech_id))
+            .count();
+        researched as f64 * 100.0 / self.technologies.len() as f64
+    }
+
+    pub fn get_completion_percentage_by_cost(&self, unlocked: &HashSet<String>) -> f64 {
+        let (researched, total) = self
+            .technologies
+            .values()
+            .fold((0u64, 0u64), |(r, t), tech| {
+                let cost = u64::from(tech.cost);
+                if unlocked.contains(&tech.id) {
+                    (r + cost, t + cost)
+                } else {
+                    (r, t + cost)
+                }
+            });
+        if total == 0 {
+            // Only free technologies, so every one of them weighs the same.
+            return self.get_completion_percentage(unlocked);
+        }
+        researched as f64 * 100.0 / total as f64
+    }
+
+    pub fn get_research_coverage(&self, budget: u32, starting_unlocked: &HashSet<String>) -> f64 {
+        if self.technologies.is_empty() {
+            return 0.0;
+        }
+        let (unlocked, _) =
+            self.simulate_n_steps(self.technologies.len(), starting_unlocked, budget, 0);
+        let covered = unlocked
+            .iter()
+            .filter(|tech_id| self.technologies.contains_key(*tech_id))
+            .count();
+        covered as f64 / self.technologies.len() as f64
+    }
+
+    /// Every sample researches the cheapest available technology as soon as it is affordable,
+    /// picking at random among equally cheap ones. The cost of a technology is the total spent
+    /// by the time it gets researched.
+    #[cfg(feature = "rand")]
+    pub fn sample_research_costs(
+        &self,
+        samples: usize,
+        income_per_turn: u32,
+    ) -> HashMap<String, (f64, f64)> {
+        use rand::seq::SliceRandom;
+
+        let mut rng = rand::thread_rng();
+        let mut costs: HashMap<&str, Vec<f64>> = HashMap::new();
+        for _ in 0..samples {
+            let mut unlocked = HashSet::new();
+            let mut points: u64 = 0;
+            let mut spent: u64 = 0;
+            loop {
+                let mut available: Vec<&Technology> = self
+                    .technologies
+                    .values()
+                    .filter(|tech| {
+                        !unlocked.contains(&tech.id) && self.prerequisites_met(tech, &unlocked)
+                    })
+                    .collect();
+                let cheapest = match available.iter().map(|tech| u64::from(tech.cost)).min() {
+                    Some(cost) if cost <= points || income_per_turn > 0 => cost,
+                    _ => break,
+                };
+                if cheapest > points {
+                    let turns = (cheapest - points).div_ceil(u64::from(income_per_turn));
+                    points += turns * u64::from(income_per_turn);
+                }
+
+                available.retain(|tech| u64::from(tech.cost) == cheapest);
+                available.sort_by(|a, b| a.id.cmp(&b.id));
+                let tech = available
+                    .choose(&mut rng)
+                    .expect("the cheapest is affordable");
+                points -= u64::from(tech.cost);
+                spent += u64::from(tech.cost);
+                unlocked.insert(tech.id.clone());
+                costs
+                    .entry(tech.id.as_str())
+                    .or_default()
+                    .push(spent as f64);
+            }
+        }
+
+        costs
+            .into_iter()
+            .map(|(tech_id, costs)| {
+                let mean = costs.iter().sum::<f64>() / costs.len() as f64;
+                let variance = costs.iter().map(|cost| (cost - mean).powi(2)).sum::<f64>()
+                    / costs.len() as f64;
+                (tech_id.to_string(), (mean, variance.sqrt()))
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "rand")]
+    pub fn get_random_valid_research_order(
+        &self,
+        rng: &mut impl rand::Rng,
+    ) -> Result<Vec<String>, TechnologyError> {
+        self.assert_no_cycles()?;
+
+        let mut unlocked = HashSet::new();
+        let mut order = Vec::with_capacity(self.technologies.len());
+        while order.len() < self.technologies.len() {
+            let mut available: Vec<&Technology> = self
+                .technologies
+                .values()
+                .filter(|tech| {
+                    !unlocked.contains(&tech.id) && self.prerequisites_met(tech, &unlocked)
+                })
+                .collect();
+            if available.is_empty() {
+                let mut remaining: Vec<String> = self
+                    .technologies
+                    .keys()
+                    .filter(|id| !unlocked.contains(*id))
+                    .cloned()
+                    .collect();
+                remaining.sort();
+                return Err(TechnologyError::Unreachable(remaining));
+            }
+
+            available.sort_by(|a, b| a.id.cmp(&b.id));
+            let tech = available[rng.gen_range(0..available.len())];
+            unlocked.insert(tech.id.clone());
+            order.push(tech.id.clone());
+        }
+
+        Ok(order)
+    }
+
+    pub fn get_technology_path_constrained(
+        &self,
+        target: &str,
+        unlocked: &HashSet<String>,
+        max_science: u32,
+        max_turns: u32,
+        income_per_turn: u32,
+    ) -> Option<Vec<String>> {
+        // Researching within `max_turns` means spending at most `max_turns * income_per_turn`.
+        let budget = u64::from(max_science).min(u64::from(max_turns) * u64::from(income_per_turn));
+        self.cheapest_research_sequence(target, unlocked, budget)
+    }
+
+    pub fn get_path_min_hops_within_budget(
+        &self,
+        target: &str,
+        unlocked: &HashSet<String>,
+        budget: u32,
+    ) -> Option<Vec<String>> {
+        self.fewest_hops_sequence(target, unlocked, u64::from(budget))
+    }
+
+    pub fn get_technology_path_bfs(
+        &self,
+        target: &str,
+        unlocked: &HashSet<String>,
+    ) -> Option<Vec<String>> {
+        self.fewest_hops_sequence(target, unlocked, u64::MAX)
+    }
+
+    fn fewest_hops_sequence(
+        &self,
+        target: &str,
+        unlocked: &HashSet<String>,
+        budget: u64,
+    ) -> Option<Vec<String>> {
+        // Every path researches one of the minimal research sets, so the smallest set that
+        // fits the budget gives the fewest hops.
+        let mut sets = self.research_sets(target, unlocked, budget);
+        sets.sort_by_cached_key(|set| {
+            let mut ids: Vec<String> = set.iter().cloned().collect();
+            ids.sort_unstable();
+            (ids.len(), self.total_cost(set), ids)
+        });
+        sets.iter()
+            .find_map(|set| self.research_in_order(set, unlocked))
+    }
+
+    pub fn get_path_within_per_step_budget(
+        &self,
+        target: &str,
+        unlocked: &HashSet<String>,
+        max_per_tech_cost: u32,
+    ) -> Option<Vec<String>> {
+        self.research_sequence(target, unlocked, |tech| tech.cost <= max_per_tech_cost)
+    }
+
+    pub fn get_minimum_research_subset(
+        &self,
+        target: &str,
+        unlocked: &HashSet<String>,
+    ) -> Option<HashSet<String>> {
+        self.cheapest_research_sequence(target, unlocked, u64::MAX)
+            .map(|sequence| sequence.into_iter().collect())
+    }
+
+    // Picks the cheapest of the minimal research sets within `budget`, so only `Or`
+    // prerequisites are branched on.
+    fn cheapest_research_sequence(
+        &self,
+        target: &str,
+        unlocked: &HashSet<String>,
+        budget: u64,
+    ) -> Option<Vec<String>> {
+        let mut sets = self.research_sets(target, unlocked, budget);
+        sets.sort_by_cached_key(|set| {
+            let mut ids: Vec<String> = set.iter().cloned().collect();
+            ids.sort_unstable();
+            (self.total_cost(set), ids.len(), ids)
+        });
+        sets.iter()
+            .find_map(|set| self.research_in_order(set, unlocked))
+    }
+
+    // Every minimal set of technologies still to research before `target` is researched,
+    // leaving out sets costing more than `budget`. A tree of `And` prerequisites yields just
+    // its closure, each `Or` multiplies the sets by its number of branches at most.
+    fn research_sets(
+        &self,
+        target: &str,
+        unlocked: &HashSet<String>,
+        budget: u64,
+    ) -> Vec<HashSet<String>> {
+        self.research_set_options(
+            target,
+            unlocked,
+            budget,
+            &mut HashMap::new(),
+            &mut HashSet::new(),
+        )
+    }
+
+    fn research_set_options(
+        &self,
+        tech_id: &str,
+        unlocked: &HashSet<String>,
+        budget: u64,
+        memo: &mut HashMap<String, Vec<HashSet<String>>>,
+        visiting: &mut HashSet<String>,
+    ) -> Vec<HashSet<String>> {
+        if unlocked.contains(tech_id) {
+            return vec![HashSet::new()];
+        }
+        if let Some(options) = memo.get(tech_id) {
+            return options.clone();
+        }
+        let tech = match self.technologies.get(tech_id) {
+            Some(tech) => tech,
+            None => return Vec::new(),
+        };
+        // A cyclic branch can never be researched, so it offers no options.
+        if !visiting.insert(tech_id.to_string()) {
+            return Vec::new();
+        }
+
+        let mut prereqs: Vec<&String> = tech.prerequisites.ids().iter().collect();
+        prereqs.sort();
+        let mut options = match &tech.prerequisites {
+            Prerequisites::And(_) => {
+                prereqs
+                    .into_iter()
+                    .fold(vec![HashSet::new()], |acc, prereq| {
+                        if acc.is_empty() {
+                            return acc;
+                        }
+                        let branch =
+                            self.research_set_options(prereq, unlocked, budget, memo, visiting);
+                        let combined = acc
+                            .iter()
+                            .flat_map(|set| {
+                                branch
+                                    .iter()
+                                    .map(move |option| set.union(option).cloned().collect())
+                            })
+                            .filter(|set| self.total_cost(set) <= budget)
+                            .collect();
+                        minimal_sets(combined)
+                    })
+            }
+            Prerequisites::Or(_) => prereqs
+                .into_iter()
+                .flat_map(|prereq| {
+                    self.research_set_options(prereq, unlocked, budget, memo, visiting)
+                })
+                .collect(),
+        };
+        for option in &mut options {
+            option.insert(tech_id.to_string());
+        }
+        options.retain(|set| self.total_cost(set) <= budget);
+        let options = minimal_sets(options);
+
+        visiting.remove(tech_id);
+        memo.insert(tech_id.to_string(), options.clone());
+        options
+    }
+
+    fn find_technology_path<F>(
+        &self,
+        target: &str,
+        unlocked: &HashSet<String>,
+        science_points: u32,
+        allowed: F,
+    ) -> Option<Vec<String>>
+    where
+        F: Fn(&Technology) -> bool,
+    {
+        self.research_sequence(target, unlocked, |tech| {
+            tech.cost <= science_points && allowed(tech)
+        })
+    }
+
+    // Takes the prerequisite closure of the target, resolving every `Or` to its cheapest
+    // branch, then researches it in prerequisite order while tracking what has been unlocked
+    // so far. Branches sharing prerequisites can make this miss the cheapest overall set,
+    // `cheapest_research_sequence` finds that one.
+    fn research_sequence<F>(
+        &self,
+        target: &str,
+        unlocked: &HashSet<String>,
+        can_research: F,
+    ) -> Option<Vec<String>>
+    where
+        F: Fn(&Technology) -> bool,
+    {
+        if unlocked.contains(target) {
+            return Some(Vec::new());
+        }
+        let required = self.required_technologies(
+            target,
+            unlocked,
+            &can_research,
+            &mut HashMap::new(),
+            &mut HashSet::new(),
+        )?;
+        self.research_in_order(&required, unlocked)
+    }
+
+    // Researches `technologies` one at a time, cheapest first among those whose
+    // prerequisites are met by everything unlocked so far.
+    fn research_in_order(
+        &self,
+        technologies: &HashSet<String>,
+        unlocked: &HashSet<String>,
+    ) -> Option<Vec<String>> {
+        let mut remaining: Vec<&Technology> = technologies
+            .iter()
+            .map(|tech_id| &self.technologies[tech_id])
+            .collect();
+        remaining.sort_by(|a, b| a.cost.cmp(&b.cost).then_with(|| a.id.cmp(&b.id)));
+
+        let mut researched = unlocked.clone();
+        let mut sequence = Vec::with_capacity(remaining.len());
+        while !remaining.is_empty() {
+            // Era thresholds aren't part of the closure, so this can still get stuck.
+            let index = remaining
+                .iter()
+                .position(|tech| self.prerequisites_met(tech, &researched))?;
+            let tech = remaining.remove(index);
+            researched.insert(tech.id.clone());
+            sequence.push(tech.id.clone());
+        }
+
+        Some(sequence)
+    }
+
+    fn required_technologies<F>(
+        &self,
+        tech_id: &str,
+        unlocked: &HashSet<String>,
+        can_research: &F,
+        required: &mut HashMap<String, Option<HashSet<String>>>,
+        visiting: &mut HashSet<String>,
+    ) -> Option<HashSet<String>>
+    where
+        F: Fn(&Technology) -> bool,
+    {
+        if unlocked.contains(tech_id) {
+            return Some(HashSet::new());
+        }
+        if let Some(technologies) = required.get(tech_id) {
+            return technologies.clone();
+        }
+        let tech = self.technologies.get(tech_id)?;
+        if !can_research(tech) || !visiting.insert(tech_id.to_string()) {
+            return None;
+        }
+
+        let mut prereqs: Vec<&String> = tech.prerequisites.ids().iter().collect();
+        prereqs.sort();
+        let technologies = match &tech.prerequisites {
+            Prerequisites::And(_) => prereqs.into_iter().try_fold(
+                HashSet::from([tech_id.to_string()]),
+                |mut technologies, prereq| {
+                    technologies.extend(self.required_technologies(
+                        prereq,
+                        unlocked,
+                        can_research,
+                        required,
+                        visiting,
+                    )?);
+                    Some(technologies)
+                },
+            ),
+            Prerequisites::Or(_) => prereqs
+                .into_iter()
+                .filter_map(|prereq| {
+                    self.required_technologies(prereq, unlocked, can_research, required, visiting)
+                })
+                .min_by_key(|technologies| self.total_cost(technologies))
+                .map(|mut technologies| {
+                    technologies.insert(tech_id.to_string());
+                    technologies
+                }),
+        };
+
+        visiting.remove(tech_id);
+        required.insert(tech_id.to_string(), technologies.clone());
+        technologies
+    }
+
+    fn total_cost(&self, technologies: &HashSet<String>) -> u64 {
+        technologies
+            .iter()
+            .filter_map(|tech_id| self.technologies.get(tech_id))
+            .map(|tech| u64::from(tech.cost))
+            .sum()
+    }
+
+    pub fn get_technology_depth(&self, tech_id: &str) -> Option<usize> {
+        if !self.technologies.contains_key(tech_id) {
+            return None;
+        }
+        let mut depths = HashMap::new();
+        Some(self.depth(tech_id, &mut depths, &mut HashSet::new()))
+    }
+
+    fn depth(
+        &self,
+        tech_id: &str,
+        depths: &mut HashMap<String, usize>,
+        visiting: &mut HashSet<String>,
+    ) -> usize {
+        if let Some(&depth) = depths.get(tech_id) {
+            return depth;
+        }
+        let tech = match self.technologies.get(tech_id) {
+            Some(tech) => tech,
+            None => return 0,
+        };
+        if !visiting.insert(tech_id.to_string()) {
+            return 0;
+        }
+
+        let mut depth = 0;
+        for prereq in tech.prerequisites.ids() {
+            if self.technologies.contains_key(prereq) {
+                depth = depth.max(self.depth(prereq, depths, visiting) + 1);
+            }
+        }
+
+        visiting.remove(tech_id);
+        depths.insert(tech_id.to_string(), depth);
+        depth
+    }
+
+    pub fn get_research_rank_by_cost(&self, tech_id: &str) -> Option<usize> {
+        self.technologies_sorted_by_cost(|_| true)
+            .iter()
+            .position(|tech| tech.id == tech_id)
+            .map(|index| index + 1)
+    }
+
+    pub fn get_research_rank_by_depth(&self, tech_id: &str) -> Option<usize> {
+        let mut depths = HashMap::new();
+        let mut ranked: Vec<(usize, &str)> = self
+            .technologies
+            .keys()
+            .map(|id| {
+                (
+                    self.depth(id, &mut depths, &mut HashSet::new()),
+                    id.as_str(),
+                )
+            })
+            .collect();
+        ranked.sort_unstable();
+        ranked
+            .iter()
+            .position(|(_, id)| *id == tech_id)
+            .map(|index| index + 1)
+    }
+
+    pub fn get_unlock_wave(&self, tech_id: &str) -> Option<usize> {
+        self.unlock_waves().get(tech_id).copied()
+    }
+
+    pub fn group_by_wave(&self) -> Vec<Vec<&Technology>> {
+        let mut groups: Vec<Vec<&Technology>> = Vec::new();
+        for (tech_id, wave) in self.unlock_waves() {
+            if groups.len() <= wave {
+                groups.resize_with(wave + 1, Vec::new);
+            }
+            groups[wave].push(&self.technologies[tech_id]);
+        }
+        for group in &mut groups {
+            group.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+        }
+        groups
+    }
+
+    // Waves are assigned level by level: a technology joins the first wave in which the
+    // technologies from earlier waves satisfy its prerequisites. Unreachable ones get none.
+    fn unlock_waves(&self) -> HashMap<&str, usize> {
+        let mut waves: HashMap<&str, usize> = HashMap::new();
+        let mut assigned: HashSet<String> = HashSet::new();
+        let mut wave = 0;
+        loop {
+            let next: Vec<&str> = self
+                .technologies
+                .values()
+                .filter(|tech| {
+                    !assigned.contains(&tech.id) && tech.prerequisites_satisfied_by(&assigned)
+                })
+                .map(|tech| tech.id.as_str())
+                .collect();
+            if next.is_empty() {
+                return waves;
+            }
+            for tech_id in next {
+                waves.insert(tech_id, wave);
+                assigned.insert(tech_id.to_string());
+            }
+            wave += 1;
+        }
+    }
+
+    pub fn get_parallel_research_depth(&self) -> usize {
+        let mut depths = HashMap::new();
+        self.technologies
+            .keys()
+            .map(|tech_id| self.depth(tech_id, &mut depths, &mut HashSet::new()) + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn get_bottleneck_depth(&self) -> Option<(String, usize)> {
+        let dependents = self.dependents_map();
+        let leaves: Vec<&str> = self
+            .technologies
+            .keys()
+            .map(String::as_str)
+            .filter(|tech_id| !dependents.contains_key(tech_id))
+            .collect();
+
+        let mut gated_leaves: HashMap<String, usize> = HashMap::new();
+        for leaf in &leaves {
+            for ancestor in self.get_all_ancestors(leaf) {
+                if self.technologies.contains_key(&ancestor) {
+                    *gated_leaves.entry(ancestor).or_default() += 1;
+                }
+            }
+        }
+
+        let mut depths = HashMap::new();
+        gated_leaves
+            .into_iter()
+            .filter(|(_, count)| count * 2 > leaves.len())
+            .map(|(tech_id, _)| {
+                let depth = self.depth(&tech_id, &mut depths, &mut HashSet::new());
+                (tech_id, depth)
+            })
+            .max_by(|(a, a_depth), (b, b_depth)| a_depth.cmp(b_depth).then_with(|| b.cmp(a)))
+    }
+
+    pub fn to_layered_layout(&self) -> HashMap<String, (f32, f32)> {
+        let mut depths = HashMap::new();
+        let mut layers: Vec<Vec<&Technology>> = Vec::new();
+        for tech_id in self.technologies.keys() {
+            let depth = self.depth(tech_id, &mut depths, &mut HashSet::new());
+            if layers.len() <= depth {
+                layers.resize_with(depth + 1, Vec::new);
+            }
+            layers[depth].push(&self.technologies[tech_id]);
+        }
+
+        let spread = |index: usize, count: usize| {
+            if count <= 1 {
+                0.5
+            } else {
+                index as f32 / (count - 1) as f32
+            }
+        };
+
+        let mut layout: HashMap<String, (f32, f32)> = HashMap::new();
+        let layer_count = layers.len();
+        for (depth, mut layer) in layers.into_iter().enumerate() {
+            // Order nodes by the mean position of their prerequisites to reduce edge crossings.
+            let barycenter = |tech: &Technology| {
+                let positions: Vec<f32> = tech
+                    .prerequisites
+                    .ids()
+                    .iter()
+                    .filter_map(|prereq| layout.get(prereq).map(|&(_, y)| y))
+                    .collect();
+                if positions.is_empty() {
+                    0.0
+                } else {
+                    positions.iter().sum::<f32>() / positions.len() as f32
+                }
+            };
+            layer.sort_by(|a, b| {
+                barycenter(a)
+                    .total_cmp(&barycenter(b))
+                    .then_with(|| a.id.cmp(&b.id))
+            });
+
+            let x = spread(depth, layer_count);
+            let positions: Vec<(String, (f32, f32))> = layer
+                .iter()
+                .enumerate()
+                .map(|(index, tech)| (tech.id.clone(), (x, spread(index, layer.len()))))
+                .collect();
+            layout.extend(positions);
+        }
+
+        layout
+    }
+
+    pub fn get_technology_path_all_variants(
+        &self,
+        target: &str,
+        unlocked: &HashSet<String>,
+        science_points: u32,
+        limit: usize,
+    ) -> Vec<Vec<String>> {
+        if limit == 0 || unlocked.contains(target) {
+            return Vec::new();
+        }
+        self.path_variants(target, unlocked, science_points, limit, &mut HashSet::new())
+    }
+
+    fn path_variants(
+        &self,
+        tech_id: &str,
+        unlocked: &HashSet<String>,
+        science_points: u32,
+        limit: usize,
+        visiting: &mut HashSet<String>,
+    ) -> Vec<Vec<String>> {
+        if unlocked.contains(tech_id) {
+            return vec![Vec::new()];
+        }
+        let tech = match self.technologies.get(tech_id) {
+            Some(tech) if tech.cost <= science_points => tech,
+            _ => return Vec::new(),
+        };
+        if !visiting.insert(tech_id.to_string()) {
+            return Vec::new();
+        }
+
+        let mut prereqs: Vec<&String> = tech.prerequisites.ids().iter().collect();
+        prereqs.sort();
+
+        let mut variants: Vec<Vec<String>> = match &tech.prerequisites {
+            Prerequisites::And(_) => {
+                // Every prerequisite has to be reached, so combine each variant of one
+                // prerequisite with each variant of the others.
+                let mut combined = vec![Vec::new()];
+                for prereq in prereqs {
+                    let branches =
+                        self.path_variants(prereq, unlocked, science_points, limit, visiting);
+                    let mut next = Vec::new();
+                    for path in &combined {
+                        for branch in &branches {
+                            let mut extended: Vec<String> = path.clone();
+                            for step in branch {
+                                if !extended.contains(step) {
+                                    extended.push(step.clone());
+                                }
+                            }
+                            if !next.contains(&extended) {
+                                next.push(extended);
+                            }
+                        }
+                    }
+                    next.truncate(limit);
+                    combined = next;
+                }
+                combined
+            }
+            Prerequisites::Or(_) => {
+                let mut alternatives = Vec::new();
+                for prereq in prereqs {
+                    for branch in
+                        self.path_variants(prereq, unlocked, science_points, limit, visiting)
+                    {
+                        if alternatives.len() < limit && !alternatives.contains(&branch) {
+                            alternatives.push(branch);
+                        }
+                    }
+                }
+                alternatives
+            }
+        };
+
+        visiting.remove(tech_id);
+        for path in &mut variants {
+            path.push(tech_id.to_string());
+        }
+        variants
+    }
+
+    pub fn print_tech_tree(&self, unlocked: &mut HashSet<String>, indent: usize) {
+        let roots: Vec<String> = self
+            .technologies
+            .iter()
+            .filter(|(_, tech)| match &tech.prerequisites {
+                Prerequisites::And(prereqs) => prereqs.is_empty() || prereqs.is_subset(unlocked),
+                Prerequisites::Or(prereqs) => prereqs.is_empty() || !prereqs.is_disjoint(unlocked),
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for root in roots {
+            self.print_tech_branch(&root, unlocked, indent);
+        }
+    }
+
+    fn print_tech_branch(&self, tech_id: &str, unlocked: &mut HashSet<String>, indent: usize) {
+        if let Some(tech) = self.technologies.get(tech_id) {
+            println!(
+                "{}- {} (Cost: {})",
+                " ".repeat(indent),
+                tech.name,
+                tech.cost
+            );
+
+            unlocked.insert(tech_id.to_string());
+
+            for neighbor_id in self.technologies.keys() {
+                // Check if this neighbor is a child of the current technology.
+                let is_child = match &self.technologies[neighbor_id].prerequisites {
+                    Prerequisites::And(prereqs) => prereqs.contains(tech_id),
+                    Prerequisites::Or(prereqs) => prereqs.contains(tech_id),
+                };
+
+                // If it is a child and it is unlockable, print it and go deeper.
+                if is_child && self.is_unlockable(neighbor_id, unlocked, u32::MAX) {
+                    self.print_tech_branch(neighbor_id, unlocked, indent + 4);
+                }
+            }
+
+            // Remove the current technology from the unlocked set before returning.
+            unlocked.remove(tech_id);
+        }
+    }
+
+    pub fn to_ascii_tree(&self) -> String {
+        let mut dependents = self.dependents_map();
+        for children in dependents.values_mut() {
+            children.sort_unstable();
+        }
+
+        let mut roots: Vec<&str> = self
+            .technologies
+            .values()
+            .filter(|tech| {
+                !tech
+                    .prerequisites
+                    .ids()
+                    .iter()
+                    .any(|prereq| self.technologies.contains_key(prereq))
+            })
+            .map(|tech| tech.id.as_str())
+            .collect();
+        roots.sort_unstable();
+
+        let mut output = String::new();
+        for root in roots {
+            self.write_ascii_branch(root, "", None, &dependents, &mut Vec::new(), &mut output);
+        }
+        output
+    }
+
+    fn write_ascii_branch<'a>(
+        &self,
+        tech_id: &'a str,
+        prefix: &str,
+        is_last: Option<bool>,
+        dependents: &HashMap<&str, Vec<&'a str>>,
+        path: &mut Vec<&'a str>,
+        output: &mut String,
+    ) {
+        let tech = &self.technologies[tech_id];
+        let (connector, child_prefix) = match is_last {
+            None => ("", prefix.to_string()),
+            Some(true) => ("`-- ", format!("{}    ", prefix)),
+            Some(false) => ("|-- ", format!("{}|   ", prefix)),
+        };
+        output.push_str(&format!(
+            "{}{}{} (cost {}, impact {})\n",
+            prefix,
+            connector,
+            tech.name,
+            tech.cost,
+            self.get_impact_score(tech_id)
+        ));
+
+        if path.contains(&tech_id) {
+            return;
+        }
+        path.push(tech_id);
+        let children = dependents.get(tech_id).map_or(&[][..], Vec::as_slice);
+        for (i, child) in children.iter().enumerate() {
+            let is_last = i + 1 == children.len();
+            self.write_ascii_branch(
+                child,
+                &child_prefix,
+                Some(is_last),
+                dependents,
+                path,
+                output,
+            );
+        }
+        path.pop();
+    }
+
+    pub fn describe(&self) -> String {
+        let cell = |value: &str| value.replace('|', "\\|").replace('\n', " ");
+
+        let mut technologies: Vec<&Technology> = self.technologies.values().collect();
+        technologies.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut output = String::from("# Technology Tree\n\n");
+        output.push_str("| ID | Name | Cost | Prerequisites | Description |\n");
+        output.push_str("|----|------|------|---------------|-------------|\n");
+        for tech in &technologies {
+            output.push_str(&format!(
+                "| {} | {} | {} | {} | {} |\n",
+                cell(&tech.id),
+                cell(&tech.name),
+                tech.cost,
+                cell(&tech.format_prerequisites(self)),
+                cell(&tech.description)
+            ));
+        }
+
+        output.push_str("\n## Tree\n\n```text\n");
+        output.push_str(&self.to_ascii_tree());
+        output.push_str("```\n");
+
+        let total_cost: u64 = technologies.iter().map(|tech| u64::from(tech.cost)).sum();
+        let average_cost = if technologies.is_empty() {
+            0.0
+        } else {
+            total_cost as f64 / technologies.len() as f64
+        };
+        let mut depths = HashMap::new();
+        let max_depth = self
+            .technologies
+            .keys()
+            .map(|id| self.depth(id, &mut depths, &mut HashSet::new()))
+            .max()
+            .unwrap_or(0);
+
+        output.push_str("\n## Statistics\n\n");
+        output.push_str(&format!("- Technologies: {}\n", self.node_count()));
+        output.push_str(&format!("- Prerequisite links: {}\n", self.edge_count()));
+        output.push_str(&format!("- Total cost: {}\n", total_cost));
+        output.push_str(&format!("- Average cost: {:.1}\n", average_cost));
+        output.push_str(&format!("- Maximum depth: {}\n", max_depth));
+        if let Some((tech, fan_out)) = self.get_max_fan_out() {
+            output.push_str(&format!(
+                "- Highest fan-out: {} ({} dependents)\n",
+                tech.name, fan_out
+            ));
+        }
+
+        let audits = [
+            ("Missing names", self.get_technologies_with_empty_name()),
+            (
+                "Missing descriptions",
+                self.get_technologies_with_empty_description(),
+            ),
+        ];
+        if audits
+            .iter()
+            .any(|(_, technologies)| !technologies.is_empty())
+        {
+            output.push_str("\n## Incomplete Entries\n\n");
+            for (title, technologies) in audits {
+                if !technologies.is_empty() {
+                    let ids: Vec<&str> = technologies.iter().map(|tech| tech.id()).collect();
+                    output.push_str(&format!("- {}: {}\n", title, ids.join(", ")));
+                }
+            }
+        }
+        output
+    }
+
+    pub fn get_technologies_with_empty_description(&self) -> Vec<&Technology> {
+        self.technologies_sorted_by_id(|tech| tech.description.trim().is_empty())
+    }
+
+    pub fn get_technologies_with_empty_name(&self) -> Vec<&Technology> {
+        self.technologies_sorted_by_id(|tech| tech.name.trim().is_empty())
+    }
+
+    fn technologies_sorted_by_id<F>(&self, predicate: F) -> Vec<&Technology>
+    where
+        F: Fn(&Technology) -> bool,
+    {
+        let mut technologies: Vec<&Technology> = self
+            .technologies
+            .values()
+            .filter(|tech| predicate(tech))
+            .collect();
+        technologies.sort_by(|a, b| a.id.cmp(&b.id));
+        technologies
+    }
+
+    pub fn serialize(&self) -> String {
+        let serialized_data: Vec<String> = self
+            .technologies_in_serialization_order()
+            .into_iter()
+            .map(serialize_technology)
+            .collect();
+        serialized_data.join("\n")
+    }
+
+    fn technologies_in_serialization_order(&self) -> Vec<&Technology> {
+        self.get_tech_ids_in_stable_order()
+            .into_iter()
+            .map(|tech_id| &self.technologies[tech_id])
+            .chain(&self.unnamed)
+            .collect()
+    }
+
+    pub fn estimate_serialized_size(&self) -> usize {
+        let digits = |value: u32| value.checked_ilog10().map_or(1, |log| log as usize + 1);
+        let joined_len = |values: &HashSet<String>| {
+            values.iter().map(String::len).sum::<usize>() + values.len().saturating_sub(1)
+        };
+
+        let lines: usize = self
+            .technologies
+            .values()
+            .chain(&self.unnamed)
+            .map(|tech| {
+                let prereqs = tech.prerequisites.kind().to_string().len()
+                    + 1
+                    + joined_len(tech.prerequisites.ids());
+                let mut size = tech.id.len()
+                    + tech.name.len()
+                    + tech.description.len()
+                    + prereqs
+                    + digits(tech.cost)
+                    + 4;
+                let cost_multi_len = tech.cost_multi.as_ref().map_or(0, |cost| {
+                    cost.resources
+                        .iter()
+                        .map(|(resource, &amount)| resource.len() + 1 + digits(amount))
+                        .sum::<usize>()
+                        + cost.resources.len().saturating_sub(1)
+                });
+                let optional = [
+                    digits(tech.era),
+                    joined_len(&tech.tags),
+                    joined_len(&tech.excludes),
+                    cost_multi_len,
+                ];
+                let count = optional_field_count(tech);
+                size += count + optional[..count].iter().sum::<usize>();
+                if is_comment(&tech.id) || tech.id.starts_with('\\') {
+                    size += 1;
+                }
+                size
+            })
+            .sum();
+        lines + self.len().saturating_sub(1)
+    }
+
+    pub fn serialize_to_writer<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        for (index, tech) in self
+            .technologies_in_serialization_order()
+            .into_iter()
+            .enumerate()
+        {
+            if index > 0 {
+                writer.write_all(b"\n")?;
+            }
+            writer.write_all(serialize_technology(tech).as_bytes())?;
+        }
+        writer.flush()
+    }
+
+    pub fn compact_serialize(&self) -> Vec<u8> {
+        let technologies = self.technologies_in_serialization_order();
+
+        let mut encoder = compact::Encoder::default();
+        encoder.write_varint(technologies.len() as u64);
+        for tech in technologies {
+            encoder.write_string(&tech.id);
+            encoder.write_string(&tech.name);
+            encoder.write_string(&tech.description);
+            encoder.write_byte(match tech.prerequisites.kind() {
+                PrerequisiteType::And => 0,
+                PrerequisiteType::Or => 1,
+            });
+            let mut prereqs: Vec<&String> = tech.prerequisites.ids().iter().collect();
+            prereqs.sort();
+            encoder.write_varint(prereqs.len() as u64);
+            for prereq in prereqs {
+                encoder.write_string(prereq);
+            }
+            encoder.write_varint(u64::from(tech.cost));
+            encoder.write_varint(u64::from(tech.era));
+            let mut tags: Vec<&String> = tech.tags.iter().collect();
+            tags.sort();
+            encoder.write_varint(tags.len() as u64);
+            for tag in tags {
+                encoder.write_string(tag);
+            }
+            let mut excludes: Vec<&String> = tech.excludes.iter().collect();
+            excludes.sort();
+            encoder.write_varint(excludes.len() as u64);
+            for exclude in excludes {
+                encoder.write_string(exclude);
+            }
+            let mut costs: Vec<(&String, &u32)> = tech
+                .cost_multi
+                .iter()
+                .flat_map(|cost| &cost.resources)
+                .collect();
+            costs.sort();
+            encoder.write_varint(costs.len() as u64);
+            for (resource, &amount) in costs {
+                encoder.write_string(resource);
+                encoder.write_varint(u64::from(amount));
+            }
+        }
+        encoder.finish()
+    }
+
+    pub fn compact_deserialize(data: &[u8]) -> Result<Self, TechnologyError> {
+        let invalid = |message: String| {
+            TechnologyError::Io(io::Error::new(io::ErrorKind::InvalidData, message))
+        };
+        let read_u32 = |decoder: &mut compact::Decoder| -> Result<u32, String> {
+            let value = decoder.read_varint()?;
+            u32::try_from(value).map_err(|_| format!("{} does not fit in 32 bits", value))
+        };
+        let read_strings = |decoder: &mut compact::Decoder| -> Result<HashSet<String>, String> {
+            let count = decoder.read_varint()?;
+            (0..count)
+                .map(|_| decoder.read_string().map(String::from))
+                .collect()
+        };
+
+        let mut decoder = compact::Decoder::new(data).map_err(invalid)?;
+        let count = decoder.read_varint().map_err(invalid)?;
+        let mut tech_tree = TechnologyTree::new();
+        for _ in 0..count {
+            let mut read_technology = || -> Result<Technology, String> {
+                let id = decoder.read_string()?.to_string();
+                let name = decoder.read_string()?.to_string();
+                let description = decoder.read_string()?.to_string();
+                let kind = decoder.read_byte()?;
+                let prereqs = read_strings(&mut decoder)?;
+                let prerequisites = match kind {
+                    0 => Prerequisites::And(prereqs),
+                    1 => Prerequisites::Or(prereqs),
+                    _ => return Err(format!("unknown prerequisite type {}", kind)),
+                };
+                let cost = read_u32(&mut decoder)?;
+                let era = read_u32(&mut decoder)?;
+                let tags = read_strings(&mut decoder)?;
+                let excludes = read_strings(&mut decoder)?;
+                let cost_count = decoder.read_varint()?;
+                let resources = (0..cost_count)
+                    .map(|_| {
+                        let resource = decoder.read_string()?.to_string();
+                        Ok((resource, read_u32(&mut decoder)?))
+                    })
+                    .collect::<Result<HashMap<String, u32>, String>>()?;
+                let technology = Technology::new(id, name, description, prerequisites, cost)
+                    .with_era(era)
+                    .with_tags(tags)
+                    .with_excludes(excludes);
+                if resources.is_empty() {
+                    Ok(technology)
+                } else {
+                    Ok(technology.with_cost_multi(MultiResourceCost { resources }))
+                }
+            };
+            let technology = read_technology().map_err(invalid)?;
+            tech_tree.load_technology(technology);
+        }
+        if !decoder.is_finished() {
+            return Err(invalid("unexpected trailing data".to_string()));
+        }
+
+        Ok(tech_tree)
+    }
+
+    pub fn serialize_with_comments(&self, comments: &[&str]) -> String {
+        let mut lines: Vec<String> = comments
+            .iter()
+            .flat_map(|comment| comment.split('\n'))
+            .map(|comment| format!("# {}", comment))
+            .collect();
+
+        let serialized = self.serialize();
+        if !serialized.is_empty() {
+            lines.push(serialized);
+        }
+
+        lines.join("\n")
+    }
+
+    pub fn export_metadata_json(&self) -> String {
+        let json_array = |values: &HashSet<String>| {
+            let mut values: Vec<&String> = values.iter().collect();
+            values.sort();
+            let values: Vec<String> = values.into_iter().map(|v| json::escape(v)).collect();
+            format!("[{}]", values.join(","))
+        };
+
+        let mut technologies: Vec<&Technology> = self.technologies.values().collect();
+        technologies.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let entries: Vec<String> = technologies
+            .iter()
+            .map(|tech| {
+                format!(
+                    "{{\"id\":{},\"name\":{},\"cost\":{},\"era\":{},\"tags\":{},\"prereq_type\":\"{}\",\"prereqs\":{}}}",
+                    json::escape(&tech.id),
+                    json::escape(&tech.name),
+                    tech.cost,
+                    tech.era,
+                    json_array(&tech.tags),
+                    tech.prerequisites.kind(),
+                    json_array(tech.prerequisites.ids())
+                )
+            })
+            .collect();
+
+        format!("[{}]", entries.join(","))
+    }
+
+    pub fn build_from_hierarchical_json(json: &str) -> Result<Self, TechnologyError> {
+        let root = json::parse(json).map_err(|e| TechnologyError::Parse {
+            line: e.line,
+            message: e.message,
+        })?;
+
+        let mut tech_tree = TechnologyTree::new();
+        match &root {
+            JsonValue::Array(nodes) => {
+                for node in nodes {
+                    tech_tree.add_json_node(node, None)?;
+                }
+            }
+            node => tech_tree.add_json_node(node, None)?,
+        }
+        Ok(tech_tree)
+    }
+
+    fn add_json_node(
+        &mut self,
+        node: &JsonValue,
+        parent_id: Option<&str>,
+    ) -> Result<(), TechnologyError> {
+        let line = match node {
+            JsonValue::Object { line, .. } => *line,
+            _ => {
+                return Err(TechnologyError::Parse {
+                    line: 0,
+                    message: "expected a technology object".to_string(),
+                })
+            }
+        };
+        let parse_error = |message: &str| TechnologyError::Parse {
+            line,
+            message: message.to_string(),
+        };
+        let string_field = |key: &str| match node.get(key) {
+            None => Ok(None),
+            Some(JsonValue::String(value)) => Ok(Some(value.clone())),
+            Some(_) => Err(parse_error(&format!("'{}' must be a string", key))),
+        };
+        let integer_field = |key: &str| match node.get(key) {
+            None => Ok(None),
+            Some(JsonValue::Number(value))
+                if value.fract() == 0.0 && (0.0..=u32::MAX as f64).contains(value) =>
+            {
+                Ok(Some(*value as u32))
+            }
+            Some(_) => Err(parse_error(&format!(
+                "'{}' must be a non-negative integer",
+                key
+            ))),
+        };
+
+        let id = string_field("id")?.ok_or_else(|| parse_error("missing 'id'"))?;
+        let prerequisites: HashSet<String> = parent_id.map(String::from).into_iter().collect();
+
+        // A technology nested under several parents requires all of them.
+        if let Some(existing) = self.technologies.get_mut(&id) {
+            existing.prerequisites.ids_mut().extend(prerequisites);
+        } else {
+            let name = string_field("name")?.unwrap_or_else(|| id.clone());
+            let description = string_field("description")?.unwrap_or_default();
+            let cost = integer_field("cost")?.unwrap_or(0);
+            let era = integer_field("era")?.unwrap_or(0);
+            let tags = match node.get("tags") {
+                None => HashSet::new(),
+                Some(JsonValue::Array(tags)) => tags
+                    .iter()
+                    .map(|tag| match tag {
+                        JsonValue::String(tag) => Ok(tag.clone()),
+                        _ => Err(parse_error("'tags' must contain strings")),
+                    })
+                    .collect::<Result<_, _>>()?,
+                Some(_) => return Err(parse_error("'tags' must be an array")),
+            };
+
+            self.insert_technology(
+                Technology::new(
+                    id.clone(),
+                    name,
+                    description,
+                    Prerequisites::And(prerequisites),
+                    cost,
+                )
+                .with_era(era)
+                .with_tags(tags),
+            );
+        }
+
+        match node.get("children") {
+            None => Ok(()),
+            Some(JsonValue::Array(children)) => {
+                for child in children {
+                    self.add_json_node(child, Some(&id))?;
+                }
+                Ok(())
+            }
+            Some(_) => Err(parse_error("'children' must be an array")),
+        }
+    }
+
+    pub fn from_dot_string(dot: &str) -> Result<Self, TechnologyError> {
+        let graph = dot::parse(dot).map_err(|e| TechnologyError::Parse {
+            line: e.line,
+            message: e.message,
+        })?;
+
+        let mut prerequisites: HashMap<&str, HashSet<String>> = HashMap::new();
+        for (from, to) in &graph.edges {
+            prerequisites
+                .entry(to.as_str())
+                .or_default()
+                .insert(from.clone());
+        }
+
+        let mut technologies = HashMap::new();
+        for node in &graph.nodes {
+            let cost = match node.attribute("cost") {
+                None => 1,
+                Some(cost) => cost.parse().map_err(|_| TechnologyError::Parse {
+                    line: node.line,
+                    message: format!("invalid cost '{}' for node {}", cost, node.id),
+                })?,
+            };
+            let technology = Technology::new(
+                node.id.clone(),
+                node.attribute("label").unwrap_or(&node.id).to_string(),
+                node.attribute("tooltip").unwrap_or_default().to_string(),
+                Prerequisites::And(prerequisites.remove(node.id.as_str()).unwrap_or_default()),
+                cost,
+            );
+            technologies.insert(node.id.clone(), technology);
+        }
+
+        Ok(TechnologyTree {
+            technologies,
+            ..Default::default()
+        })
+    }
+
+    pub fn deserialize(data: &str) -> Self {
+        let mut tech_tree = TechnologyTree::new();
+
+        for (index, line) in data.lines().enumerate() {
+            if is_comment(line) {
+                continue;
+            }
+            // Malformed lines are skipped and unparsable numbers read as 0, like older versions
+            // did, use `TryFrom<&str>` to have them reported instead.
+            if let Ok(technology) = parse_technology(line, index + 1, false) {
+                tech_tree.load_technology(technology);
+            }
+        }
+
+        tech_tree
+    }
+
+    pub fn deserialize_from_reader<R: io::BufRead>(reader: R) -> Result<Self, TechnologyError> {
+        let mut tech_tree = TechnologyTree::new();
+
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() || is_comment(&line) {
+                continue;
+            }
+            let technology = parse_technology(&line, index + 1, true)?;
+            tech_tree.load_technology(technology);
+        }
+
+        Ok(tech_tree)
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(filename: P) -> io::Result<Self> {
+        let data = fs::read_to_string(filename)?;
+        let tech_tree = TechnologyTree::deserialize(&data);
+        Ok(tech_tree)
+    }
+
+    pub fn load_from_directory(dir: &str, extension: &str) -> io::Result<Self> {
+        let extension = extension.trim_start_matches('.');
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == extension) {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        let mut merged = TechnologyTree::new();
+        for path in paths {
+            let tech_tree = TechnologyTree::try_from(path.as_path()).map_err(|e| {
+                let kind = match &e {
+                    TechnologyError::Io(io_error) => io_error.kind(),
+                    _ => io::ErrorKind::InvalidData,
+                };
+                io::Error::new(kind, format!("{}: {}", path.display(), e))
+            })?;
+            for technology in tech_tree
+                .technologies
+                .into_values()
+                .chain(tech_tree.unnamed)
+            {
+                merged.load_technology(technology);
+            }
+        }
+
+        Ok(merged)
+    }
+
+    #[cfg(feature = "csv")]
+    pub fn import_from_csv(reader: impl io::Read) -> Result<TechnologyTree, TechnologyError> {
+        let mut csv_reader = csv::Reader::from_reader(reader);
+        let mut tech_tree = TechnologyTree::new();
+
+        for record in csv_reader.records() {
+            let record = record.map_err(csv_error)?;
+            let line = record.position().map_or(0, |p| p.line() as usize);
+            let fields: Vec<&str> = record.iter().collect();
+            let technology = technology_from_fields(&fields, true)
+                .map_err(|message| TechnologyError::Parse { line, message })?;
+            tech_tree.load_technology(technology);
+        }
+
+        Ok(tech_tree)
+    }
+
+    #[cfg(feature = "csv")]
+    pub fn export_to_csv(&self, writer: impl io::Write) -> io::Result<()> {
+        let mut csv_writer = csv::Writer::from_writer(writer);
+        let technologies = self.technologies_in_serialization_order();
+        // Every row needs the same columns, so the optional ones are written for all or none.
+        let extended = technologies
+            .iter()
+            .any(|tech| optional_field_count(tech) > 0);
+        let mut header = vec!["id", "name", "description", "prerequisites", "cost"];
+        if extended {
+            header.extend(["era", "tags", "excludes", "cost_multi"]);
+        }
+        csv_writer.write_record(&header)?;
+
+        for tech in technologies {
+            let mut record = vec![
+                tech.id.clone(),
+                tech.name.clone(),
+                tech.description.clone(),
+                serialize_prerequisites(&tech.prerequisites),
+                tech.cost.to_string(),
+            ];
+            if extended {
+                record.extend(optional_fields(tech));
+            }
+            csv_writer.write_record(&record)?;
+        }
+
+        csv_writer.flush()
+    }
+}
+
+fn shortest_path_counts<'a>(
+    source: &'a str,
+    dependents: &HashMap<&'a str, Vec<&'a str>>,
+) -> HashMap<&'a str, (usize, f64)> {
+    let mut counts: HashMap<&str, (usize, f64)> = HashMap::from([(source, (0, 1.0))]);
+    let mut queue = VecDeque::from([source]);
+
+    while let Some(current) = queue.pop_front() {
+        let (distance, paths) = counts[current];
+        for &dependent in dependents.get(current).into_iter().flatten() {
+            match counts.get_mut(dependent) {
+                None => {
+                    counts.insert(dependent, (distance + 1, paths));
+                    queue.push_back(dependent);
+                }
+                Some((dependent_distance, dependent_paths))
+                    if *dependent_distance == distance + 1 =>
+                {
+                    *dependent_paths += paths;
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    counts
+}
+
+fn snake_case(name: &str) -> String {
+    let mut id = String::new();
+    for c in name.chars() {
+        if c.is_alphanumeric() {
+            id.extend(c.to_lowercase());
+        } else if !id.is_empty() && !id.ends_with('_') {
+            id.push('_');
+        }
+    }
+    let id = id.trim_end_matches('_');
+    if id.is_empty() {
+        "technology".to_string()
+    } else {
+        id.to_string()
+    }
+}
+
+fn is_comment(line: &str) -> bool {
+    line.trim_start().starts_with('#')
+}
+
+#[cfg(feature = "csv")]
+fn csv_error(e: csv::Error) -> TechnologyError {
+    let line = e.position().map_or(0, |p| p.line() as usize);
+    let message = e.to_string();
+    match e.into_kind() {
+        csv::ErrorKind::Io(e) => TechnologyError::Io(e),
+        _ => TechnologyError::Parse { line, message },
+    }
+}
+
+fn parse_technology(
+    line: &str,
+    line_number: usize,
+    strict: bool,
+) -> Result<Technology, TechnologyError> {
+    let line = line.strip_prefix('\\').unwrap_or(line);
+    let fields: Vec<&str> = line.split(';').collect();
+    technology_from_fields(&fields, strict).map_err(|message| TechnologyError::Parse {
+        line: line_number,
+        message,
+    })
+}
+
+fn technology_from_fields(fields: &[&str], strict: bool) -> Result<Technology, String> {
+    if !(5..=9).contains(&fields.len()) {
+        return Err(format!("expected 5 to 9 fields, found {}", fields.len()));
+    }
+
+    let (tech_id, name, description, prereqs, cost) =
+        (fields[0], fields[1], fields[2], fields[3], fields[4]);
+    let prerequisites = parse_prerequisites(prereqs)?;
+    let number = |field: &str, value: &str| match value.parse::<u32>() {
+        Ok(number) => Ok(number),
+        Err(_) if !strict => Ok(0),
+        Err(_) => Err(format!("invalid {} '{}'", field, value)),
+    };
+    let cost = number("cost", cost)?;
+    let era = match fields.get(5) {
+        Some(era) => number("era", era)?,
+        None => 0,
+    };
+    let list = |index: usize| -> HashSet<String> {
+        fields
+            .get(index)
+            .map(|values| {
+                values
+                    .split(',')
+                    .filter(|s| !s.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let tags = list(6);
+    let excludes = list(7);
+    let cost_multi = match fields.get(8).filter(|costs| !costs.is_empty()) {
+        Some(costs) => {
+            let resources = costs
+                .split(',')
+                .filter(|s| !s.is_empty())
+                .map(|entry| {
+                    let (resource, amount) = entry
+                        .split_once(':')
+                        .ok_or_else(|| format!("invalid resource cost '{}'", entry))?;
+                    Ok((resource.to_string(), number("resource cost", amount)?))
+                })
+                .collect::<Result<_, String>>()?;
+            Some(MultiResourceCost { resources })
+        }
+        None => None,
+    };
+
+    Ok(Technology {
+        id: tech_id.to_string(),
+        name: name.to_string(),
+        description: description.to_string(),
+        prerequisites,
+        cost,
+        era,
+        tags,
+        excludes,
+        cost_multi,
+        version: 0,
+    })
+}
+
+fn parse_prerequisites(prereqs: &str) -> Result<Prerequisites, String> {
+    let (kind, list) = prereqs
+        .split_once(':')
+        .ok_or_else(|| format!("invalid prerequisites '{}'", prereqs))?;
+    let prereq_set: HashSet<String> = list
+        .split(',')
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect();
+
+    match kind {
+        "And" => Ok(Prerequisites::And(prereq_set)),
+        "Or" => Ok(Prerequisites::Or(prereq_set)),
+        _ => Err(format!("unknown prerequisite type '{}'", kind)),
+    }
+}
+
+fn minimal_sets(mut sets: Vec<HashSet<String>>) -> Vec<HashSet<String>> {
+    sets.sort_by_key(HashSet::len);
+    let mut minimal: Vec<HashSet<String>> = Vec::new();
+    for set in sets {
+        if !minimal.iter().any(|kept| kept.is_subset(&set)) {
+            minimal.push(set);
+        }
+    }
+    minimal
+}
+
+fn serialize_technology(tech: &Technology) -> String {
+    let mut line = format!(
+        "{};{};{};{};{}",
+        tech.id,
+        tech.name,
+        tech.description,
+        serialize_prerequisites(&tech.prerequisites),
+        tech.cost
+    );
+    // A leading backslash keeps ids such as "#1" from being read back as comments.
+    if is_comment(&line) || line.starts_with('\\') {
+        line.insert(0, '\\');
+    }
+    let count = optional_field_count(tech);
+    for field in &optional_fields(tech)[..count] {
+        line.push(';');
+        line.push_str(field);
+    }
+    line
+}
+
+// The era, tags, excludes and multi-resource cost fields are optional, so trees without them
+// keep the original format.
+fn optional_fields(tech: &Technology) -> [String; 4] {
+    let sorted = |values: &HashSet<String>| {
+        let mut values: Vec<&str> = values.iter().map(String::as_str).collect();
+        values.sort_unstable();
+        values.join(",")
+    };
+    let mut costs: Vec<String> = tech
+        .cost_multi
+        .iter()
+        .flat_map(|cost| &cost.resources)
+        .map(|(resource, amount)| format!("{}:{}", resource, amount))
+        .collect();
+    costs.sort_unstable();
+    [
+        tech.era.to_string(),
+        sorted(&tech.tags),
+        sorted(&tech.excludes),
+        costs.join(","),
+    ]
+}
+
+// How many optional fields have to be written, trailing ones left at their default are not.
+fn optional_field_count(tech: &Technology) -> usize {
+    let used = [
+        tech.era != 0,
+        !tech.tags.is_empty(),
+        !tech.excludes.is_empty(),
+        tech.cost_multi
+            .as_ref()
+            .is_some_and(|cost| !cost.resources.is_empty()),
+    ];
+    used.iter()
+        .rposition(|&used| used)
+        .map_or(0, |index| index + 1)
+}
+
+fn serialize_prerequisites(prerequisites: &Prerequisites) -> String {
+    let mut ids: Vec<&str> = prerequisites.ids().iter().map(String::as_str).collect();
+    ids.sort_unstable();
+    format!("{}:{}", prerequisites.kind(), ids.join(","))
+}
+
+impl TryFrom<&str> for TechnologyTree {
+    type Error = TechnologyError;
+
+    fn try_from(data: &str) -> Result<Self, Self::Error> {
+        TechnologyTree::deserialize_from_reader(data.as_bytes())
+    }
+}
+
+impl FromStr for TechnologyTree {
+    type Err = TechnologyError;
+
+    fn from_str(data: &str) -> Result<Self, Self::Err> {
+        TechnologyTree::try_from(data)
+    }
+}
+
+impl fmt::Display for TechnologyTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.serialize())
+    }
+}
+
+impl TryFrom<&Path> for TechnologyTree {
+    type Error = TechnologyError;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        let data = fs::read_to_string(path)?;
+        TechnologyTree::try_from(data.as_str())
+    }
+}
+
+impl TechTreeBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_technology(&mut self, technology: Technology) -> &mut Self {
+        self.technologies.push(technology);
+        self
+    }
+
+    pub fn build(&self) -> Result<TechnologyTree, TechnologyError> {
+        let mut tech_tree = TechnologyTree::new();
+        for tech in &self.technologies {
+            if tech_tree.technologies.contains_key(&tech.id) {
+                return Err(TechnologyError::DuplicateId(tech.id.clone()));
+            }
+            tech_tree.insert_technology(tech.clone());
+        }
+
+        tech_tree.validate()?;
+        Ok(tech_tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "static_assertions")]
+    mod thread_safety {
+        use super::*;
+
+        static_assertions::assert_impl_all!(TechnologyTree: Send, Sync);
+        static_assertions::assert_impl_all!(Technology: Send, Sync);
+        static_assertions::assert_impl_all!(Prerequisites: Send, Sync);
+        static_assertions::assert_impl_all!(TechnologyError: Send, Sync);
+    }
+
+    #[test]
+    fn test_add_technology() {
+        let mut tech_tree = TechnologyTree::new();
+        let tech = Technology {
+            id: "pottery".to_string(),
+            name: "Pottery".to_string(),
+            description: "Basic pottery techniques.".to_string(),
+            prerequisites: Prerequisites::And(HashSet::new()),
+            cost: 5,
+            era: 0,
+            tags: HashSet::new(),
+            excludes: HashSet::new(),
+            cost_multi: None,
+            version: 0,
+        };
+
+        tech_tree.add_technology(tech.clone());
+
+        assert!(tech_tree.technologies.contains_key(&tech.id));
+    }
+
+    #[test]
+    fn test_is_unlockable() {
+        let mut tech_tree = TechnologyTree::new();
+        let mut unlocked = HashSet::new();
+        unlocked.insert("pottery".to_string());
+
+        let tech = Technology {
+            id: "writing".to_string(),
+            name: "Writing".to_string(),
+            description: "Basics of writing.".to_string(),
+            prerequisites: Prerequisites::And(unlocked.clone()),
+            cost: 10,
+            era: 0,
+            tags: HashSet::new(),
+            excludes: HashSet::new(),
+            cost_multi: None,
+            version: 0,
+        };
+
+        tech_tree.add_technology(tech.clone());
+
+        assert!(tech_tree.is_unlockable(&tech.id, &unlocked, 15));
+    }
+
+    #[test]
+    fn test_remove_technology() {
+        let mut tech_tree = TechnologyTree::new();
+        let tech_id = "pottery".to_string();
+        let tech = Technology {
+            id: tech_id.clone(),
+            name: "Pottery".to_string(),
+            description: "Basic pottery techniques.".to_string(),
+            prerequisites: Prerequisites::And(HashSet::new()),
+            cost: 5,
+            era: 0,
+            tags: HashSet::new(),
+            excludes: HashSet::new(),
+            cost_multi: None,
+            version: 0,
+        };
+        tech_tree.add_technology(tech);
+
+        assert!(tech_tree.remove_technology(&tech_id).is_ok());
+        assert!(!tech_tree.technologies.contains_key(&tech_id));
+    }
+
+    #[test]
+    fn test_remove_technology_with_dependency() {
+        let mut tech_tree = TechnologyTree::new();
+        let mut prereq = HashSet::new();
+        prereq.insert("pottery".to_string());
+
+        let tech1 = Technology {
+            id: "pottery".to_string(),
+            name: "Pottery".to_string(),
+            description: "Basic pottery techniques.".to_string(),
+            prerequisites: Prerequisites::And(HashSet::new()),
+            cost: 5,
+            era: 0,
+            tags: HashSet::new(),
+            excludes: HashSet::new(),
+            cost_multi: None,
+            version: 0,
+        };
+
+        let tech2 = Technology {
+            id: "irrigation".to_string(),
+            name: "Irrigation".to_string(),
+            description: "Advanced irrigation techniques.".to_string(),
+            prerequisites: Prerequisites::And(prereq.clone()),
+            cost: 10,
+            era: 0,
+            tags: HashSet::new(),
+            excludes: HashSet::new(),
+            cost_multi: None,
+            version: 0,
+        };
+
+        tech_tree.add_technology(tech1);
+        tech_tree.add_technology(tech2);
+
+        assert!(tech_tree.remove_technology("pottery").is_err());
+    }
+
+    #[test]
+    fn test_unlock_technology() {
+        let mut tech_tree = TechnologyTree::new();
+        let mut unlocked = HashSet::new();
+        unlocked.insert("pottery".to_string());
+
+        let tech = Technology {
+            id: "writing".to_string(),
+            name: "Writing".to_string(),
+            description: "Basics of writing.".to_string(),
+            prerequisites: Prerequisites::And(unlocked.clone()),
+            cost: 10,
+            era: 0,
+            tags: HashSet::new(),
+            excludes: HashSet::new(),
+            cost_multi: None,
+            version: 0,
+        };
+
+        tech_tree.add_technology(tech.clone());
+
+        assert!(tech_tree.unlock_technology(&tech.id, &mut unlocked, 15));
+    }
+
+    #[test]
+    fn test_try_from_str() {
+        let data = "pottery;Pottery;Basic pottery techniques.;And:;5\n\
+                    writing;Writing;Basics of writing.;And:pottery;10";
+
+        let tech_tree = TechnologyTree::try_from(data).unwrap();
+
+        assert_eq!(tech_tree.technologies.len(), 2);
+        assert_eq!(tech_tree.technologies["writing"].cost, 10);
+    }
+
+    #[test]
+    fn test_try_from_str_reports_malformed_line() {
+        let data = "pottery;Pottery;Basic pottery techniques.;And:;5\n\
+                    writing;Writing;Basics of writing.;And:pottery;ten";
+
+        match TechnologyTree::try_from(data) {
+            Err(TechnologyError::Parse { line, .. }) => assert_eq!(line, 2),
+            other => panic!("expected parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_try_from_missing_path() {
+        let result = TechnologyTree::try_from(Path::new("does_not_exist.txt"));
+
+        assert!(matches!(result, Err(TechnologyError::Io(_))));
+    }
+
+    #[test]
+    fn test_technology_builders() {
+        let mut tech_tree = TechnologyTree::new();
+        tech_tree.add_technology(Technology::new(
+            "pottery".to_string(),
+            "Pottery".to_string(),
+            "Basic pottery techniques.".to_string(),
+            Prerequisites::And(HashSet::new()),
+            5,
+        ));
+
+        let hard_pottery = tech_tree
+            .get_technology("pottery")
+            .unwrap()
+            .clone()
+            .with_cost(20)
+            .with_name("Hard Pottery".to_string())
+            .with_description("Pottery, but harder.".to_string());
+
+        assert_eq!(hard_pottery.id(), "pottery");
+        assert_eq!(hard_pottery.cost(), 20);
+        assert_eq!(hard_pottery.name(), "Hard Pottery");
+        assert_eq!(hard_pottery.description(), "Pottery, but harder.");
+        assert_eq!(tech_tree.get_technology("pottery").unwrap().cost(), 5);
+    }
+
+    #[test]
+    fn test_get_parallel_research_sets() {
+        let mut tech_tree = TechnologyTree::new();
+        for (id, prereqs) in [
+            ("pottery", vec![]),
+            ("mining", vec![]),
+            ("writing", vec!["pottery"]),
+            ("masonry", vec!["mining"]),
+        ] {
+            tech_tree.add_technology(Technology::new(
+                id.to_string(),
+                id.to_string(),
+                String::new(),
+                Prerequisites::And(prereqs.into_iter().map(String::from).collect()),
+                5,
+            ));
+        }
+
+        let unlocked = HashSet::from(["pottery".to_string()]);
+        let sets = tech_tree.get_parallel_research_sets(&unlocked, 10);
+
+        assert_eq!(sets.len(), 1);
+        assert_eq!(
+            sets[0],
+            HashSet::from(["mining".to_string(), "writing".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_get_mandatory_predecessors() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             masonry;Masonry;;And:mining;10\n\
+             bronze;Bronze Working;;And:mining;10\n\
+             construction;Construction;;Or:masonry,bronze;20\n\
+             engineering;Engineering;;And:construction,writing;30",
+        );
+
+        let mandatory = tech_tree.get_mandatory_predecessors("engineering", &HashSet::new());
+        assert_eq!(
+            mandatory,
+            ["construction", "mining", "writing", "pottery"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect()
+        );
+
+        let unlocked = HashSet::from(["pottery".to_string(), "writing".to_string()]);
+        let mandatory = tech_tree.get_mandatory_predecessors("engineering", &unlocked);
+        assert_eq!(
+            mandatory,
+            HashSet::from(["construction".to_string(), "mining".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_comments_are_skipped() {
+        let data = "# Ancient era\n\
+                    pottery;Pottery;Basic pottery techniques.;And:;5\n\
+                    # writing;Writing;Basics of writing.;And:pottery;10";
+
+        let tech_tree = TechnologyTree::try_from(data).unwrap();
+        assert_eq!(tech_tree.technologies.len(), 1);
+
+        let tech_tree = TechnologyTree::deserialize(data);
+        assert_eq!(tech_tree.technologies.len(), 1);
+    }
+
+    #[test]
+    fn test_serialize_with_comments() {
+        let tech_tree =
+            TechnologyTree::deserialize("pottery;Pottery;Basic pottery techniques.;And:;5");
+
+        let serialized = tech_tree.serialize_with_comments(&["Tech tree", "Version 1"]);
+
+        assert_eq!(
+            serialized,
+            "# Tech tree\n# Version 1\npottery;Pottery;Basic pottery techniques.;And:;5"
+        );
+        assert_eq!(
+            TechnologyTree::deserialize(&serialized).technologies.len(),
+            1
+        );
+
+        let serialized = tech_tree.serialize_with_comments(&["Tech tree\nVersion 2"]);
+        assert!(serialized.starts_with("# Tech tree\n# Version 2\n"));
+        assert_eq!(serialized.parse::<TechnologyTree>().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_serialize_escapes_comment_like_ids() {
+        let mut tech_tree = TechnologyTree::new();
+        tech_tree.add_technology(new_tech("#1", &[]));
+        tech_tree.add_technology(new_tech("\\2", &["#1"]));
+
+        let serialized = tech_tree.to_string();
+        assert_eq!(serialized.len(), tech_tree.estimate_serialized_size());
+
+        let parsed = serialized.parse::<TechnologyTree>().unwrap();
+        assert_eq!(parsed.get_tech_ids_in_stable_order(), vec!["#1", "\\2"]);
+        assert_eq!(
+            parsed.technologies["\\2"].prerequisites(),
+            &Prerequisites::and_from_strs(&["#1"])
+        );
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn test_csv_round_trip() {
+        let data = "id,name,description,prerequisites,cost\n\
+                    pottery,Pottery,Basic pottery techniques.,And:,5\n\
+                    writing,Writing,\"Basics of writing, reading.\",And:pottery,10\n";
+
+        let tech_tree = TechnologyTree::import_from_csv(data.as_bytes()).unwrap();
+        assert_eq!(tech_tree.technologies.len(), 2);
+        assert_eq!(
+            tech_tree.technologies["writing"].description,
+            "Basics of writing, reading."
+        );
+
+        let mut exported = Vec::new();
+        tech_tree.export_to_csv(&mut exported).unwrap();
+        assert_eq!(String::from_utf8(exported).unwrap(), data);
+
+        let extended = "id,name,description,prerequisites,cost,era,tags,excludes,cost_multi\n\
+                        pottery,Pottery,,And:,5,0,,,\n\
+                        writing,Writing,,And:pottery,10,1,science,,\"gold:5,science:10\"\n";
+        let tech_tree = TechnologyTree::import_from_csv(extended.as_bytes()).unwrap();
+        assert_eq!(
+            tech_tree.technologies["writing"]
+                .cost_multi()
+                .unwrap()
+                .resources["gold"],
+            5
+        );
+        let mut exported = Vec::new();
+        tech_tree.export_to_csv(&mut exported).unwrap();
+        assert_eq!(String::from_utf8(exported).unwrap(), extended);
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn test_import_from_csv_reports_invalid_cost() {
+        let data = "id,name,description,prerequisites,cost\n\
+                    pottery,Pottery,Basic pottery techniques.,And:,five\n";
+
+        let result = TechnologyTree::import_from_csv(data.as_bytes());
+
+        assert!(matches!(
+            result,
+            Err(TechnologyError::Parse { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_get_technology_path_excluding() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             irrigation;Irrigation;;And:pottery;10",
+        );
+        let unlocked = HashSet::from(["pottery".to_string()]);
+
+        assert!(tech_tree
+            .get_technology_path_excluding("writing", &unlocked, 10, &HashSet::new())
+            .is_some());
+
+        let excluded = HashSet::from(["writing".to_string()]);
+        assert!(tech_tree
+            .get_technology_path_excluding("writing", &unlocked, 10, &excluded)
+            .is_none());
+        assert!(tech_tree
+            .get_technology_path_excluding("irrigation", &unlocked, 10, &excluded)
+            .is_some());
+    }
+
+    #[test]
+    fn test_get_technology_names_and_ids() {
+        let tech_tree = TechnologyTree::deserialize(
+            "writing;Writing;;And:pottery;10\n\
+             pottery;Pottery;;And:;5\n\
+             bronze;Bronze Working;;And:;5",
+        );
+
+        assert_eq!(
+            tech_tree.get_technology_names(),
+            vec!["Bronze Working", "Pottery", "Writing"]
+        );
+        assert_eq!(
+            tech_tree.get_technology_ids(),
+            vec!["bronze", "pottery", "writing"]
+        );
+    }
+
+    #[test]
+    fn test_get_technology_depth() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             education;Education;;And:writing,mining;20",
+        );
+
+        assert_eq!(tech_tree.get_technology_depth("pottery"), Some(0));
+        assert_eq!(tech_tree.get_technology_depth("education"), Some(2));
+        assert_eq!(tech_tree.get_technology_depth("unknown"), None);
+    }
+
+    #[test]
+    fn test_to_layered_layout() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             masonry;Masonry;;And:mining;10\n\
+             education;Education;;And:writing;20",
+        );
+
+        let layout = tech_tree.to_layered_layout();
+
+        assert_eq!(layout.len(), 5);
+        assert_eq!(layout["mining"], (0.0, 0.0));
+        assert_eq!(layout["pottery"], (0.0, 1.0));
+        assert_eq!(layout["masonry"], (0.5, 0.0));
+        assert_eq!(layout["writing"], (0.5, 1.0));
+        assert_eq!(layout["education"], (1.0, 0.5));
+        assert!(layout
+            .values()
+            .all(|&(x, y)| (0.0..=1.0).contains(&x) && (0.0..=1.0).contains(&y)));
+    }
+
+    #[test]
+    fn test_get_technology_path_all_variants() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             masonry;Masonry;;And:mining;10\n\
+             bronze;Bronze Working;;And:mining;10\n\
+             construction;Construction;;Or:masonry,bronze;20",
+        );
+        let unlocked = HashSet::from(["pottery".to_string()]);
+
+        let variants =
+            tech_tree.get_technology_path_all_variants("construction", &unlocked, 20, 10);
+        assert_eq!(
+            variants,
+            vec![
+                vec!["mining", "bronze", "construction"],
+                vec!["mining", "masonry", "construction"],
+            ]
+        );
+
+        let variants = tech_tree.get_technology_path_all_variants("construction", &unlocked, 20, 1);
+        assert_eq!(variants.len(), 1);
+
+        let variants =
+            tech_tree.get_technology_path_all_variants("construction", &unlocked, 10, 10);
+        assert!(variants.is_empty());
+    }
+
+    #[test]
+    fn test_node_and_edge_count() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             construction;Construction;;Or:pottery,mining;20",
+        );
+
+        assert_eq!(tech_tree.node_count(), 4);
+        assert_eq!(tech_tree.edge_count(), 3);
+        assert!(TechnologyTree::new().is_empty());
+    }
+
+    #[test]
+    fn test_get_average_path_length() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             masonry;Masonry;;And:mining;10\n\
+             education;Education;;And:writing;20",
+        );
+
+        // Leaves are masonry (1 hop) and education (2 hops).
+        assert_eq!(tech_tree.get_average_path_length(), 1.5);
+        assert_eq!(TechnologyTree::new().get_average_path_length(), 0.0);
+    }
+
+    #[test]
+    fn test_era_threshold() {
+        let mut tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             bronze;Bronze Working;;And:mining;10;1",
+        );
+        tech_tree.set_era_threshold(1, 2);
+
+        let mut unlocked = HashSet::from(["mining".to_string()]);
+        assert!(!tech_tree.is_unlockable("bronze", &unlocked, 10));
+
+        unlocked.insert("pottery".to_string());
+        assert!(tech_tree.is_unlockable("bronze", &unlocked, 10));
+    }
+
+    #[test]
+    fn test_era_serialization_round_trip() {
+        let data = "bronze;Bronze Working;;And:;10;1";
+
+        let tech_tree = TechnologyTree::try_from(data).unwrap();
+
+        assert_eq!(tech_tree.technologies["bronze"].era(), 1);
+        assert_eq!(tech_tree.serialize(), data);
+    }
+
+    #[test]
+    fn test_get_technologies_in_era_order() {
+        let tech_tree = TechnologyTree::deserialize(
+            "writing;Writing;;And:;10\n\
+             pottery;Pottery;;And:;5\n\
+             iron;Iron Working;;And:;20;2\n\
+             bronze;Bronze Working;;And:;10;1\n\
+             currency;Currency;;And:;10;1",
+        );
+
+        let ordered: Vec<&str> = tech_tree
+            .get_technologies_in_era_order()
+            .iter()
+            .map(|tech| tech.id())
+            .collect();
+        assert_eq!(
+            ordered,
+            vec!["pottery", "writing", "bronze", "currency", "iron"]
+        );
+        assert_eq!(
+            tech_tree.get_era_boundaries(),
+            vec![(0, 0, 2), (1, 2, 4), (2, 4, 5)]
+        );
+    }
+
+    #[test]
+    fn test_export_metadata_json() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5;0;economy\n\
+             writing;\"Writing\";;Or:pottery;10;1",
+        );
+
+        assert_eq!(
+            tech_tree.export_metadata_json(),
+            "[{\"id\":\"pottery\",\"name\":\"Pottery\",\"cost\":5,\"era\":0,\"tags\":[\"economy\"],\
+             \"prereq_type\":\"And\",\"prereqs\":[]},\
+             {\"id\":\"writing\",\"name\":\"\\\"Writing\\\"\",\"cost\":10,\"era\":1,\"tags\":[],\
+             \"prereq_type\":\"Or\",\"prereqs\":[\"pottery\"]}]"
+        );
+    }
+
+    #[test]
+    fn test_tags_serialization_round_trip() {
+        let data = "pottery;Pottery;;And:;5;0;crafting,economy";
+
+        let tech_tree = TechnologyTree::try_from(data).unwrap();
+
+        assert_eq!(tech_tree.technologies["pottery"].tags().len(), 2);
+        assert_eq!(tech_tree.serialize(), data);
+    }
+
+    #[test]
+    fn test_get_technology_influence_score() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             irrigation;Irrigation;;And:pottery;10\n\
+             education;Education;;And:writing;20\n\
+             philosophy;Philosophy;;And:writing,irrigation;20",
+        );
+
+        // Writing lies on the only shortest path from pottery to education and
+        // on one of the two shortest paths from pottery to philosophy.
+        assert_eq!(tech_tree.get_technology_influence_score("writing"), 1.5);
+        assert_eq!(tech_tree.get_technology_influence_score("pottery"), 0.0);
+        assert_eq!(tech_tree.get_technology_influence_score("education"), 0.0);
+    }
+
+    #[test]
+    fn test_find_alternative_path() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             writing;Writing;;Or:pottery;10\n\
+             irrigation;Irrigation;;Or:pottery;10",
+        );
+        let unlocked = HashSet::from(["pottery".to_string()]);
+
+        assert!(tech_tree
+            .find_alternative_path("irrigation", "writing", &unlocked, 10)
+            .is_some());
+        assert!(tech_tree
+            .find_alternative_path("writing", "writing", &unlocked, 10)
+            .is_none());
+    }
+
+    #[test]
+    fn test_compute_turns_to_target() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             writing;Writing;;And:pottery;10",
+        );
+        let unlocked = HashSet::from(["pottery".to_string()]);
+
+        assert_eq!(
+            tech_tree.compute_turns_to_target("writing", &unlocked, 3, 3),
+            Some(3)
+        );
+        assert_eq!(
+            tech_tree.compute_turns_to_target("writing", &unlocked, 10, 0),
+            Some(0)
+        );
+        assert_eq!(
+            tech_tree.compute_turns_to_target("writing", &unlocked, 0, 0),
+            None
+        );
+        assert_eq!(
+            tech_tree.compute_turns_to_target("pottery", &unlocked, 0, 1),
+            Some(0)
+        );
+        assert_eq!(
+            tech_tree.compute_turns_to_target("unknown", &unlocked, 0, 1),
+            None
+        );
+
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;4000000000\n\
+             writing;Writing;;And:pottery;4000000000",
+        );
+        assert_eq!(
+            tech_tree.compute_turns_to_target("writing", &HashSet::new(), 0, 1),
+            Some(u32::MAX)
+        );
+        assert_eq!(
+            tech_tree.compute_turns_to_target("writing", &HashSet::new(), 0, 4),
+            Some(2_000_000_000)
+        );
+    }
+
+    #[test]
+    fn test_simulate_n_steps() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;8\n\
+             writing;Writing;;And:pottery;10",
+        );
+
+        let (unlocked, points) = tech_tree.simulate_n_steps(3, &HashSet::new(), 0, 6);
+
+        // Step 1 researches pottery (6 - 5 = 1), step 2 can't afford anything (7)
+        // and step 3 researches mining (13 - 8 = 5).
+        assert_eq!(
+            unlocked,
+            HashSet::from(["pottery".to_string(), "mining".to_string()])
+        );
+        assert_eq!(points, 5);
+    }
+
+    #[test]
+    fn test_get_prerequisite_edges() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             construction;Construction;;Or:pottery,mining;20",
+        );
+
+        assert_eq!(
+            tech_tree.get_prerequisite_edges(),
+            vec![
+                (
+                    "mining".to_string(),
+                    "construction".to_string(),
+                    PrerequisiteType::Or
+                ),
+                (
+                    "pottery".to_string(),
+                    "construction".to_string(),
+                    PrerequisiteType::Or
+                ),
+                (
+                    "pottery".to_string(),
+                    "writing".to_string(),
+                    PrerequisiteType::And
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_estimated_turns() {
+        let tech = Technology::new(
+            "writing".to_string(),
+            "Writing".to_string(),
+            "Basics of writing.".to_string(),
+            Prerequisites::And(HashSet::new()),
+            10,
+        );
+
+        assert_eq!(tech.estimated_turns(3), 4);
+        assert_eq!(tech.estimated_turns(10), 1);
+        assert_eq!(tech.estimated_turns(0), u32::MAX);
+    }
+
+    fn new_tech(id: &str, prereqs: &[&str]) -> Technology {
+        Technology::new(
+            id.to_string(),
+            id.to_string(),
+            String::new(),
+            Prerequisites::And(prereqs.iter().map(|s| s.to_string()).collect()),
+            5,
+        )
+    }
+
+    // A small linear congruential generator, so generated trees are reproducible without rand.
+    // Each call returns a number below `bound`.
+    fn lcg(mut seed: u64) -> impl FnMut(usize) -> usize {
+        move |bound| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as usize % bound
+        }
+    }
+
+    #[test]
+    fn test_tech_tree_builder() {
+        let tech_tree = TechTreeBuilder::new()
+            .add_technology(new_tech("pottery", &[]))
+            .add_technology(new_tech("writing", &["pottery"]))
+            .build()
+            .unwrap();
+
+        assert_eq!(tech_tree.len(), 2);
+    }
+
+    #[test]
+    fn test_tech_tree_builder_rejects_invalid_trees() {
+        let duplicate = TechTreeBuilder::new()
+            .add_technology(new_tech("pottery", &[]))
+            .add_technology(new_tech("pottery", &[]))
+            .build();
+        assert!(matches!(duplicate, Err(TechnologyError::DuplicateId(id)) if id == "pottery"));
+
+        let dangling = TechTreeBuilder::new()
+            .add_technology(new_tech("writing", &["pottery"]))
+            .build();
+        assert!(matches!(
+            dangling,
+            Err(TechnologyError::DanglingReference { prerequisite, .. }) if prerequisite == "pottery"
+        ));
+
+        let cyclic = TechTreeBuilder::new()
+            .add_technology(new_tech("pottery", &["writing"]))
+            .add_technology(new_tech("writing", &["pottery"]))
+            .build();
+        match cyclic {
+            Err(TechnologyError::Cycle(cycle)) => {
+                assert_eq!(cycle, vec!["pottery", "writing", "pottery"])
+            }
+            other => panic!("expected cycle error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_to_string_parse_round_trip() {
+        let tech_tree: TechnologyTree = "pottery;Pottery;Basic pottery techniques.;And:;5\n\
+                                         writing;Writing;Basics of writing.;And:pottery;10;1"
+            .parse()
+            .unwrap();
+
+        let parsed = tech_tree.to_string().parse::<TechnologyTree>().unwrap();
+
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed.technologies["writing"].era(), 1);
+        assert_eq!(parsed.technologies["writing"].cost(), 10);
+        assert!("pottery;Pottery".parse::<TechnologyTree>().is_err());
+    }
+
+    #[test]
+    fn test_get_technologies_by_cost() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             masonry;Masonry;;And:;15\n\
+             education;Education;;And:writing;20",
+        );
+        let ids = |technologies: Vec<&Technology>| -> Vec<String> {
+            technologies.iter().map(|tech| tech.id.clone()).collect()
+        };
+
+        assert_eq!(
+            ids(tech_tree.get_technologies_with_cost_in_range(10, 20)),
+            vec!["writing", "masonry", "education"]
+        );
+        assert_eq!(
+            ids(tech_tree.get_technologies_with_cost_below(10)),
+            vec!["pottery"]
+        );
+        assert_eq!(
+            ids(tech_tree.get_technologies_with_cost_above(10)),
+            vec!["masonry", "education"]
+        );
+    }
+
+    #[test]
+    fn test_get_root_technologies_within_budget() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;8\n\
+             sailing;Sailing;;And:;20\n\
+             masonry;Masonry;;And:mining;10\n\
+             bronze;Bronze Working;;And:mining;10\n\
+             writing;Writing;;And:pottery;10",
+        );
+
+        assert_eq!(
+            tech_tree.get_cheapest_root_technology(10).unwrap().id(),
+            "pottery"
+        );
+        assert_eq!(
+            tech_tree
+                .get_most_impactful_root_technology(10)
+                .unwrap()
+                .id(),
+            "mining"
+        );
+        assert_eq!(
+            tech_tree
+                .get_most_impactful_root_technology(5)
+                .unwrap()
+                .id(),
+            "pottery"
+        );
+        assert!(tech_tree.get_cheapest_root_technology(4).is_none());
+        assert_eq!(tech_tree.get_direct_dependents("mining").len(), 2);
+    }
+
+    #[test]
+    fn test_serialize_is_deterministic() {
+        let tech_tree = TechnologyTree::deserialize(
+            "writing;Writing;;And:pottery;10\n\
+             pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             construction;Construction;;Or:pottery,mining,writing;20",
+        );
+
+        let serialized = tech_tree.serialize();
+
+        assert_eq!(serialized, tech_tree.serialize());
+        assert_eq!(
+            serialized,
+            "construction;Construction;;Or:mining,pottery,writing;20\n\
+             mining;Mining;;And:;5\n\
+             pottery;Pottery;;And:;5\n\
+             writing;Writing;;And:pottery;10"
+        );
+    }
+
+    #[test]
+    fn test_get_technology_count_by_tag() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5;0;economy\n\
+             writing;Writing;;And:pottery;10;0;science,culture\n\
+             education;Education;;And:writing;20;0;science\n\
+             bronze;Bronze Working;;And:;10;0;military,economy",
+        );
+
+        let counts = tech_tree.get_technology_count_by_tag();
+
+        assert_eq!(counts.len(), 4);
+        assert_eq!(counts["science"], 2);
+        assert_eq!(counts["military"], 1);
+        // Ties are broken alphabetically.
+        assert_eq!(tech_tree.get_most_common_tag(), Some("economy"));
+        assert_eq!(TechnologyTree::new().get_most_common_tag(), None);
+    }
+
+    #[test]
+    fn test_find_common_prerequisites() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             education;Education;;And:writing;20\n\
+             currency;Currency;;And:writing,mining;20\n\
+             masonry;Masonry;;And:mining;10",
+        );
+
+        assert_eq!(
+            tech_tree.find_common_prerequisites("education", "currency"),
+            HashSet::from(["pottery".to_string(), "writing".to_string()])
+        );
+        assert!(tech_tree
+            .find_common_prerequisites("education", "masonry")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_get_independent_technologies() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             sailing;Sailing;;And:;10",
+        );
+
+        let independent: Vec<&str> = tech_tree
+            .get_independent_technologies()
+            .iter()
+            .map(|tech| tech.id())
+            .collect();
+        assert_eq!(independent, vec!["sailing"]);
+        assert!(matches!(
+            tech_tree.validate(),
+            Err(TechnologyError::IndependentTechnology(id)) if id == "sailing"
+        ));
+
+        let single = TechnologyTree::deserialize("sailing;Sailing;;And:;10");
+        assert!(single.validate().is_ok());
+    }
+
+    #[test]
+    fn test_get_sibling_groups() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             irrigation;Irrigation;;And:pottery;10\n\
+             currency;Currency;;And:writing,mining;20\n\
+             masonry;Masonry;;And:mining;10\n\
+             education;Education;;And:writing;20",
+        );
+
+        assert_eq!(
+            tech_tree.get_sibling_groups(),
+            vec![
+                vec!["currency", "education", "masonry"],
+                vec!["irrigation", "writing"],
+                vec!["mining", "pottery"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_subtree_cost() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             currency;Currency;;And:writing,mining;20\n\
+             education;Education;;And:writing;20",
+        );
+
+        assert_eq!(tech_tree.get_subtree_cost("pottery"), 55);
+        assert_eq!(tech_tree.get_subtree_cost("mining"), 25);
+        assert_eq!(tech_tree.get_subtree_cost("unknown"), 0);
+    }
+
+    #[test]
+    fn test_get_subtree_cost_with_cycle() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:writing;5\n\
+             writing;Writing;;And:pottery;10",
+        );
+
+        assert_eq!(tech_tree.get_subtree_cost("pottery"), 15);
+    }
+
+    #[test]
+    fn test_build_from_hierarchical_json() {
+        let json = r#"{
+            "id": "pottery",
+            "name": "Pottery",
+            "cost": 5,
+            "children": [
+                {"id": "writing", "cost": 10, "tags": ["science"], "children": [
+                    {"id": "education", "cost": 20}
+                ]},
+                {"id": "irrigation", "cost": 10, "children": [
+                    {"id": "education"}
+                ]}
+            ]
+        }"#;
+
+        let tech_tree = TechnologyTree::build_from_hierarchical_json(json).unwrap();
+
+        assert_eq!(tech_tree.len(), 4);
+        assert_eq!(tech_tree.technologies["pottery"].name(), "Pottery");
+        assert_eq!(tech_tree.technologies["writing"].name(), "writing");
+        assert!(tech_tree.technologies["writing"].tags().contains("science"));
+        assert_eq!(tech_tree.technologies["education"].cost(), 20);
+        assert_eq!(
+            tech_tree.technologies["education"].prerequisites().ids(),
+            &HashSet::from(["writing".to_string(), "irrigation".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_build_from_hierarchical_json_errors() {
+        let missing_id = "[\n{\"id\": \"pottery\"},\n{\"cost\": 5}\n]";
+        assert!(matches!(
+            TechnologyTree::build_from_hierarchical_json(missing_id),
+            Err(TechnologyError::Parse { line: 3, .. })
+        ));
+
+        let invalid = "{\"id\": \"pottery\",";
+        assert!(TechnologyTree::build_from_hierarchical_json(invalid).is_err());
+
+        let nested = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+        assert!(matches!(
+            TechnologyTree::build_from_hierarchical_json(&nested),
+            Err(TechnologyError::Parse { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_assign_auto_ids() {
+        let mut tech_tree = TechnologyTree::deserialize(
+            "iron_working;Iron Working;;And:;5\n\
+             ;Iron Working;;And:;10\n\
+             smelting;Smelting;;And:Iron Working;10",
+        );
+
+        tech_tree.assign_auto_ids();
+
+        assert!(!tech_tree.technologies.contains_key(""));
+        assert_eq!(tech_tree.technologies["iron_working_2"].cost(), 10);
+        assert_eq!(
+            tech_tree.technologies["iron_working_2"].id(),
+            "iron_working_2"
+        );
+        assert_eq!(
+            tech_tree.technologies["smelting"].prerequisites().ids(),
+            &HashSet::from(["iron_working_2".to_string()])
+        );
+        assert_eq!(tech_tree.version(), 1);
+        assert_eq!(tech_tree.get_technology_version("smelting"), Some(1));
+        assert_eq!(
+            tech_tree.get_tech_id_for_name("Iron Working"),
+            Some("iron_working")
+        );
+
+        let version = tech_tree.version();
+        tech_tree.assign_auto_ids();
+        assert_eq!(tech_tree.version(), version);
+        assert_eq!(snake_case("  Bronze-Age  Warfare!"), "bronze_age_warfare");
+    }
+
+    #[test]
+    fn test_assign_auto_ids_for_name_only_trees() {
+        let mut tech_tree: TechnologyTree = ";Pottery;;And:;5\n\
+                                             ;Writing;;And:Pottery;10\n\
+                                             ;Iron Working;;And:Writing;10\n\
+                                             ;Writing;;Or:Pottery;20"
+            .parse()
+            .unwrap();
+        assert_eq!(tech_tree.len(), 4);
+        assert_eq!(
+            tech_tree
+                .to_string()
+                .parse::<TechnologyTree>()
+                .unwrap()
+                .len(),
+            4
+        );
+
+        tech_tree.assign_auto_ids();
+
+        assert_eq!(tech_tree.len(), 4);
+        assert_eq!(
+            tech_tree.get_tech_ids_in_stable_order(),
+            vec!["iron_working", "pottery", "writing", "writing_2"]
+        );
+        assert_eq!(tech_tree.technologies["writing"].cost(), 10);
+        assert_eq!(tech_tree.technologies["writing_2"].cost(), 20);
+        assert_eq!(
+            tech_tree.technologies["writing"].prerequisites(),
+            &Prerequisites::and_from_strs(&["pottery"])
+        );
+        assert_eq!(
+            tech_tree.technologies["iron_working"].prerequisites(),
+            &Prerequisites::and_from_strs(&["writing"])
+        );
+        assert!(tech_tree.validate().is_ok());
+    }
+
+    #[test]
+    fn test_format_prerequisites() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             farming;Farming;;And:;5\n\
+             education;Education;;And:writing,pottery;20\n\
+             trade;Trade;;Or:writing,pottery,farming;20\n\
+             currency;Currency;;Or:writing,pottery;20",
+        );
+        let format = |id: &str| tech_tree.technologies[id].format_prerequisites(&tech_tree);
+
+        assert_eq!(format("pottery"), "No prerequisites");
+        assert_eq!(format("writing"), "Requires Pottery");
+        assert_eq!(format("education"), "Requires Pottery and Writing");
+        assert_eq!(format("currency"), "Requires any of: Pottery or Writing");
+        assert_eq!(
+            format("trade"),
+            "Requires any of: Farming, Pottery, or Writing"
+        );
+    }
+
+    #[test]
+    fn test_get_adjacency_matrix() {
+        let tech_tree = TechnologyTree::deserialize(
+            "writing;Writing;;And:pottery;10\n\
+             pottery;Pottery;;And:;5\n\
+             education;Education;;And:writing;20",
+        );
+
+        let (ids, matrix) = tech_tree.get_adjacency_matrix();
+
+        assert_eq!(ids, vec!["education", "pottery", "writing"]);
+        assert_eq!(
+            matrix,
+            vec![
+                vec![false, false, false],
+                vec![false, false, true],
+                vec![true, false, false],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_name_index() {
+        let mut tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             writing;Writing;;And:pottery;10",
+        );
+        assert_eq!(tech_tree.get_tech_id_for_name("Writing"), None);
+
+        tech_tree.build_name_index();
+
+        assert_eq!(tech_tree.get_tech_id_for_name("Writing"), Some("writing"));
+        assert_eq!(tech_tree.get_tech_id_for_name("Sailing"), None);
+    }
+
+    #[test]
+    fn test_is_unlockable_multi() {
+        let mut tech_tree = TechnologyTree::deserialize("pottery;Pottery;;And:;5");
+        tech_tree.add_technology(
+            Technology::new(
+                "currency".to_string(),
+                "Currency".to_string(),
+                String::new(),
+                Prerequisites::And(HashSet::from(["pottery".to_string()])),
+                10,
+            )
+            .with_cost_multi(MultiResourceCost {
+                resources: HashMap::from([("science".to_string(), 10), ("gold".to_string(), 20)]),
+            }),
+        );
+        let unlocked = HashSet::from(["pottery".to_string()]);
+        let mut resources = HashMap::from([("science".to_string(), 10)]);
+
+        assert!(tech_tree.is_unlockable_multi("pottery", &HashSet::new(), &resources));
+        assert!(!tech_tree.is_unlockable_multi("currency", &unlocked, &resources));
+
+        resources.insert("gold".to_string(), 20);
+        assert!(tech_tree.is_unlockable_multi("currency", &unlocked, &resources));
+        assert!(!tech_tree.is_unlockable_multi("currency", &HashSet::new(), &resources));
+    }
+
+    #[test]
+    fn test_cost_multi_round_trip() {
+        let mut tech_tree = TechnologyTree::deserialize("pottery;Pottery;;And:;5");
+        tech_tree.add_technology(new_tech("currency", &["pottery"]).with_cost_multi(
+            MultiResourceCost {
+                resources: HashMap::from([("science".to_string(), 10), ("gold".to_string(), 5)]),
+            },
+        ));
+        let expected = tech_tree.technologies["currency"].cost_multi().cloned();
+
+        let serialized = tech_tree.to_string();
+        assert!(serialized.contains("currency;currency;;And:pottery;5;0;;;gold:5,science:10"));
+        assert_eq!(serialized.len(), tech_tree.estimate_serialized_size());
+        let parsed = serialized.parse::<TechnologyTree>().unwrap();
+        assert_eq!(
+            parsed.technologies["currency"].cost_multi().cloned(),
+            expected
+        );
+        assert_eq!(parsed.technologies["pottery"].cost_multi(), None);
+
+        let restored = TechnologyTree::compact_deserialize(&tech_tree.compact_serialize()).unwrap();
+        assert_eq!(
+            restored.technologies["currency"].cost_multi().cloned(),
+            expected
+        );
+        assert_eq!(restored.serialize(), serialized);
+
+        assert!("a;A;;And:;5;0;;;gold".parse::<TechnologyTree>().is_err());
+    }
+
+    #[test]
+    fn test_get_technology_path_constrained() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             masonry;Masonry;;And:mining;30\n\
+             bronze;Bronze Working;;And:mining;10\n\
+             construction;Construction;;Or:masonry,bronze;20\n\
+             engineering;Engineering;;And:construction,writing;30",
+        );
+        let unlocked = HashSet::new();
+
+        let path = tech_tree
+            .get_technology_path_constrained("engineering", &unlocked, 80, 8, 10)
+            .unwrap();
+        assert_eq!(path.len(), 6);
+        assert_eq!(path.last().unwrap(), "engineering");
+        assert!(path.contains(&"bronze".to_string()));
+        assert!(!path.contains(&"masonry".to_string()));
+
+        assert!(tech_tree
+            .get_technology_path_constrained("engineering", &unlocked, 79, 8, 10)
+            .is_none());
+        assert!(tech_tree
+            .get_technology_path_constrained("engineering", &unlocked, 80, 7, 10)
+            .is_none());
+
+        // The cheapest `Or` branch on its own is not the cheapest path once shared
+        // prerequisites are counted.
+        let tech_tree = TechnologyTree::deserialize(
+            "p;P;;And:;1\n\
+             q;Q;;And:;5\n\
+             x;X;;Or:p,q;1\n\
+             y;Y;;And:q;1\n\
+             t;T;;And:x,y;1",
+        );
+        assert_eq!(
+            tech_tree.get_technology_path_constrained("t", &unlocked, 8, 8, 1),
+            Some(["q", "x", "y", "t"].map(String::from).to_vec())
+        );
+    }
+
+    #[test]
+    fn test_update_technology_with_version_check() {
+        let mut tech_tree = TechnologyTree::new();
+        tech_tree.add_technology(new_tech("pottery", &[]));
+        let tree_version = tech_tree.version();
+
+        let first_edit = tech_tree.get_technology("pottery").unwrap().clone();
+        let second_edit = first_edit.clone().with_cost(30);
+
+        assert!(tech_tree
+            .update_technology(first_edit.with_cost(20))
+            .is_ok());
+        assert_eq!(tech_tree.get_technology_version("pottery"), Some(1));
+        assert_eq!(tech_tree.get_technology("pottery").unwrap().cost(), 20);
+        assert_eq!(tech_tree.version(), tree_version + 1);
+
+        match tech_tree.update_technology(second_edit) {
+            Err(TechnologyError::VersionConflict {
+                expected, found, ..
+            }) => assert_eq!((expected, found), (1, 0)),
+            other => panic!("expected version conflict, got {:?}", other),
+        }
+        assert_eq!(tech_tree.get_technology("pottery").unwrap().cost(), 20);
+
+        assert!(matches!(
+            tech_tree.update_technology(new_tech("writing", &[])),
+            Err(TechnologyError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_get_research_milestones() {
+        let tech_tree = TechnologyTree::deserialize(
+            "a;A;;And:;10\n\
+             b;B;;And:a;10\n\
+             c;C;;And:b;20\n\
+             d;D;;And:c;60",
+        );
+
+        let milestones: Vec<&str> = tech_tree
+            .get_research_milestones()
+            .iter()
+            .map(|tech| tech.unwrap().id())
+            .collect();
+        assert_eq!(milestones, vec!["b", "c", "d", "d"]);
+
+        assert!(TechnologyTree::new()
+            .get_research_milestones()
+            .iter()
+            .all(Option::is_none));
+    }
+
+    #[test]
+    fn test_describe() {
+        let tech_tree = TechnologyTree::deserialize(
+            "a;Agriculture;Farming|crops;And:;10\n\
+             b;Bronze;;And:a;20\n\
+             c;Calendar;;And:a;30\n\
+             d;Drama;;Or:b,c;40",
+        );
+
+        assert_eq!(
+            tech_tree.to_ascii_tree(),
+            concat!(
+                "Agriculture (cost 10, impact 3)\n",
+                "|-- Bronze (cost 20, impact 1)\n",
+                "|   `-- Drama (cost 40, impact 0)\n",
+                "`-- Calendar (cost 30, impact 1)\n",
+                "    `-- Drama (cost 40, impact 0)\n",
+            )
+        );
+
+        let description = tech_tree.describe();
+        assert!(description.starts_with("# Technology Tree\n"));
+        assert!(
+            description.contains("| a | Agriculture | 10 | No prerequisites | Farming\\|crops |")
+        );
+        assert!(description.contains("| d | Drama | 40 | Requires any of: Bronze or Calendar |  |"));
+        assert!(description.contains("```text\nAgriculture (cost 10, impact 3)\n"));
+        assert!(description.contains("- Technologies: 4\n"));
+        assert!(description.contains("- Average cost: 25.0\n"));
+        assert!(description.contains("- Maximum depth: 2\n"));
+        assert!(description.contains("- Highest fan-out: Agriculture (2 dependents)\n"));
+        assert!(description.ends_with("## Incomplete Entries\n\n- Missing descriptions: b, c, d\n"));
+    }
+
+    #[test]
+    fn test_get_full_unlock_sequence() {
+        let tech_tree = TechnologyTree::deserialize(
+            "a;A;;And:;10\n\
+             b;B;;And:;5\n\
+             c;C;;And:a;10\n\
+             d;D;;And:a,b;10\n\
+             e;E;;And:d;10",
+        );
+
+        assert_eq!(
+            tech_tree.get_full_unlock_sequence(10).unwrap(),
+            vec!["a", "b", "d", "c", "e"]
+        );
+        assert_eq!(
+            tech_tree.get_full_unlock_sequence(3).unwrap(),
+            vec!["b", "a", "d", "c", "e"]
+        );
+        assert!(matches!(
+            tech_tree.get_full_unlock_sequence(0),
+            Err(TechnologyError::Unreachable(_))
+        ));
+
+        let cyclic = TechnologyTree::deserialize("a;A;;And:b;1\nb;B;;And:a;1");
+        assert!(matches!(
+            cyclic.get_full_unlock_sequence(10),
+            Err(TechnologyError::Cycle(_))
+        ));
+
+        let islands = TechnologyTree::deserialize(
+            "a;A;;And:;1\n\
+             b;B;;And:a;1\n\
+             c;C;;And:;1\n\
+             d;D;;And:c;1",
+        );
+        match islands.get_full_unlock_sequence(10) {
+            Err(TechnologyError::Disconnected(ids)) => assert_eq!(ids, vec!["c", "d"]),
+            other => panic!("expected disconnected error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cost_percentile() {
+        let tech_tree = TechnologyTree::deserialize(
+            "a;A;;And:;10\n\
+             b;B;;And:a;20\n\
+             c;C;;And:a;20\n\
+             d;D;;And:b;40",
+        );
+
+        assert_eq!(tech_tree.get_cost_percentile(5), 0.0);
+        assert_eq!(tech_tree.get_cost_percentile(20), 0.75);
+        assert_eq!(tech_tree.get_cost_percentile(100), 1.0);
+
+        assert_eq!(tech_tree.get_cost_at_percentile(0.0), 10);
+        assert_eq!(tech_tree.get_cost_at_percentile(0.5), 20);
+        assert_eq!(tech_tree.get_cost_at_percentile(0.75), 20);
+        assert_eq!(tech_tree.get_cost_at_percentile(1.0), 40);
+        assert_eq!(TechnologyTree::new().get_cost_at_percentile(0.5), 0);
+    }
+
+    #[test]
+    fn test_get_minimum_research_subset() {
+        let tech_tree = TechnologyTree::deserialize(
+            "a;A;;And:;10\n\
+             b;B;;And:a;50\n\
+             c;C;;And:a;5\n\
+             d;D;;And:c;5\n\
+             e;E;;Or:b,d;10",
+        );
+
+        let subset = tech_tree
+            .get_minimum_research_subset("e", &HashSet::new())
+            .unwrap();
+        assert_eq!(
+            subset,
+            HashSet::from(["a", "c", "d", "e"].map(String::from))
+        );
+
+        let unlocked = HashSet::from(["a".to_string(), "b".to_string()]);
+        assert_eq!(
+            tech_tree.get_minimum_research_subset("e", &unlocked),
+            Some(HashSet::from(["e".to_string()]))
+        );
+        assert_eq!(
+            tech_tree.get_minimum_research_subset("missing", &unlocked),
+            None
+        );
+
+        // Independent `And` roots leave nothing to choose between.
+        let mut tech_tree = TechnologyTree::new();
+        let roots: Vec<String> = (0..40).map(|i| format!("root_{:02}", i)).collect();
+        for root in &roots {
+            tech_tree.add_technology(new_tech(root, &[]));
+        }
+        let roots: Vec<&str> = roots.iter().map(String::as_str).collect();
+        tech_tree.add_technology(new_tech("target", &roots));
+        let subset = tech_tree
+            .get_minimum_research_subset("target", &HashSet::new())
+            .unwrap();
+        assert_eq!(subset.len(), 41);
+    }
+
+    #[test]
+    fn test_get_cost_histogram() {
+        let tech_tree = TechnologyTree::deserialize(
+            "a;A;;And:;0\n\
+             b;B;;And:a;9\n\
+             c;C;;And:a;10\n\
+             d;D;;And:b;35\n\
+             e;E;;And:b;39",
+        );
+
+        assert_eq!(
+            tech_tree.get_cost_histogram(10),
+            vec![(0, 2), (10, 1), (30, 2)]
+        );
+        assert!(TechnologyTree::new().get_cost_histogram(10).is_empty());
+    }
+
+    #[test]
+    fn test_load_from_directory() {
+        let dir = std::env::temp_dir().join(format!("tech_tree_dir_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("base.txt"), "pottery;Pottery;;And:;5\n").unwrap();
+        fs::write(
+            dir.join("expansion.txt"),
+            "writing;Writing;;And:pottery;10\n",
+        )
+        .unwrap();
+        fs::write(dir.join("notes.md"), "not a tech tree").unwrap();
+
+        let tech_tree = TechnologyTree::load_from_directory(dir.to_str().unwrap(), "txt").unwrap();
+        assert_eq!(tech_tree.get_technology_ids(), vec!["pottery", "writing"]);
+
+        fs::write(dir.join("broken.txt"), "mining;Mining").unwrap();
+        let error = TechnologyTree::load_from_directory(dir.to_str().unwrap(), ".txt").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("broken.txt"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_normalize_costs() {
+        let mut tech_tree = TechnologyTree::deserialize(
+            "a;A;;And:;10\n\
+             b;B;;And:a;10\n\
+             c;C;;And:a;10\n\
+             d;D;;And:b;30",
+        );
+        let total = |tree: &TechnologyTree| -> u32 {
+            tree.get_technology_ids()
+                .iter()
+                .map(|id| tree.get_technology(id).unwrap().cost())
+                .sum()
+        };
+
+        tech_tree.normalize_costs(120);
+        assert_eq!(total(&tech_tree), 120);
+        assert_eq!(tech_tree.get_technology("a").unwrap().cost(), 20);
+        assert_eq!(tech_tree.get_technology("d").unwrap().cost(), 60);
+
+        tech_tree.normalize_costs(100);
+        assert!(total(&tech_tree).abs_diff(100) <= 1);
+        assert_eq!(tech_tree.get_technology("a").unwrap().cost(), 17);
+
+        let stale = tech_tree.get_technology("b").unwrap().clone();
+        tech_tree.normalize_costs(1000);
+        assert_eq!(
+            tech_tree.get_technology_version("b"),
+            Some(stale.version() + 1)
+        );
+        assert!(matches!(
+            tech_tree.update_technology(stale),
+            Err(TechnologyError::VersionConflict { .. })
+        ));
+
+        let version = tech_tree.version();
+        tech_tree.normalize_costs(1000);
+        assert_eq!(tech_tree.version(), version);
+    }
+
+    #[test]
+    fn test_get_technology_chain() {
+        let tech_tree = TechnologyTree::deserialize(
+            "a;A;;And:;10\n\
+             b;B;;And:a;10\n\
+             c;C;;And:b;10\n\
+             d;D;;And:c;10\n\
+             e;E;;And:a;10\n\
+             f;F;;And:d,e;10",
+        );
+
+        assert_eq!(tech_tree.get_technology_chain("c"), vec!["b", "c", "d"]);
+        assert_eq!(tech_tree.get_technology_chain("b"), vec!["b", "c", "d"]);
+        assert_eq!(tech_tree.get_technology_chain("f"), vec!["f"]);
+        assert!(tech_tree.get_technology_chain("missing").is_empty());
+    }
+
+    #[test]
+    fn test_assert_no_cycles() {
+        let tech_tree = TechnologyTree::deserialize("a;A;;And:;1\nb;B;;And:a;1");
+        assert!(tech_tree.assert_no_cycles().is_ok());
+
+        let cyclic = TechnologyTree::deserialize("a;A;;And:c;1\nb;B;;And:a;1\nc;C;;And:b;1");
+        match cyclic.assert_no_cycles() {
+            Err(TechnologyError::Cycle(cycle)) => assert_eq!(cycle, vec!["a", "c", "b", "a"]),
+            other => panic!("expected cycle error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "prerequisite cycle")]
+    fn test_add_technology_panics_on_cycle_in_debug() {
+        let mut tech_tree = TechnologyTree::new();
+        tech_tree.add_technology(new_tech("a", &["b"]));
+        tech_tree.add_technology(new_tech("b", &["a"]));
+    }
+
+    #[test]
+    fn test_get_connected_components() {
+        let tech_tree = TechnologyTree::deserialize(
+            "a;A;;And:;1\n\
+             b;B;;And:a;1\n\
+             c;C;;And:;1\n\
+             d;D;;Or:b,c;1\n\
+             x;X;;And:;1\n\
+             y;Y;;And:x;1\n\
+             z;Z;;And:;1",
+        );
+
+        let components = tech_tree.get_connected_components();
+        assert_eq!(
+            components,
+            vec![
+                HashSet::from(["a", "b", "c", "d"].map(String::from)),
+                HashSet::from(["x", "y"].map(String::from)),
+                HashSet::from(["z".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_path_within_per_step_budget() {
+        let tech_tree = TechnologyTree::deserialize(
+            "a;A;;And:;5\n\
+             b;B;;And:a;25\n\
+             c;C;;And:a;15\n\
+             d;D;;And:c;15\n\
+             e;E;;Or:b,d;10",
+        );
+        let unlocked = HashSet::from(["a".to_string()]);
+
+        assert_eq!(
+            tech_tree.get_path_within_per_step_budget("e", &unlocked, 50),
+            Some(vec!["b".to_string(), "e".to_string()])
+        );
+        assert_eq!(
+            tech_tree.get_path_within_per_step_budget("e", &unlocked, 20),
+            Some(vec!["c".to_string(), "d".to_string(), "e".to_string()])
+        );
+        assert_eq!(
+            tech_tree.get_path_within_per_step_budget("e", &unlocked, 12),
+            None
+        );
+    }
+
+    #[test]
+    fn test_prerequisites_constructors() {
+        assert_eq!(
+            Prerequisites::and_from_strs(&["pottery", "writing"]),
+            Prerequisites::And(HashSet::from([
+                "pottery".to_string(),
+                "writing".to_string()
+            ]))
+        );
+        assert_eq!(
+            "Or:pottery,writing".parse::<Prerequisites>().unwrap(),
+            Prerequisites::or_from_strs(&["writing", "pottery"])
+        );
+        assert_eq!(
+            "And:".parse::<Prerequisites>().unwrap(),
+            Prerequisites::none()
+        );
+        assert!(matches!(
+            "Xor:pottery".parse::<Prerequisites>(),
+            Err(TechnologyError::Parse { .. })
+        ));
+    }
+
+    #[test]
+    fn test_get_impact_score() {
+        let tech_tree = TechnologyTree::deserialize(
+            "a;A;;And:;1\n\
+             b;B;;And:a;1\n\
+             c;C;;And:a;1\n\
+             d;D;;And:b,c;1",
+        );
+
+        assert_eq!(tech_tree.get_impact_score("a"), 3);
+        assert_eq!(tech_tree.get_impact_score("b"), 1);
+        assert_eq!(tech_tree.get_impact_score("d"), 0);
+        assert_eq!(tech_tree.get_impact_score("missing"), 0);
+    }
+
+    #[test]
+    fn test_get_technology_path_tracks_researched_prerequisites() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             masonry;Masonry;;And:mining;10\n\
+             architecture;Architecture;;And:writing,masonry;20",
+        );
+        let unlocked = HashSet::from(["pottery".to_string()]);
+
+        let path = tech_tree
+            .get_technology_path("architecture", &unlocked, 20)
+            .unwrap();
+        assert_eq!(path.last().map(String::as_str), Some("architecture"));
+        assert_eq!(
+            path.iter().map(String::as_str).collect::<HashSet<_>>(),
+            HashSet::from(["mining", "writing", "masonry", "architecture"])
+        );
+
+        let mut researched = unlocked.clone();
+        for tech_id in &path {
+            assert!(tech_tree.is_unlockable(tech_id, &researched, u32::MAX));
+            researched.insert(tech_id.clone());
+        }
+
+        assert_eq!(
+            tech_tree.get_technology_path("architecture", &unlocked, 10),
+            None
+        );
+        assert_eq!(
+            tech_tree.get_technology_path("pottery", &unlocked, 10),
+            Some(Vec::new())
+        );
+
+        // Many independent prerequisites used to blow up the search.
+        let mut tech_tree = TechnologyTree::new();
+        let roots: Vec<String> = (0..40).map(|i| format!("root_{:02}", i)).collect();
+        for root in &roots {
+            tech_tree.add_technology(new_tech(root, &[]));
+        }
+        let roots: Vec<&str> = roots.iter().map(String::as_str).collect();
+        tech_tree.add_technology(new_tech("target", &roots));
+        let path = tech_tree
+            .get_technology_path("target", &HashSet::new(), 5)
+            .unwrap();
+        assert_eq!(path.len(), 41);
+        assert_eq!(path.last().map(String::as_str), Some("target"));
+    }
+
+    #[test]
+    fn test_get_path_min_hops_within_budget() {
+        let tech_tree = TechnologyTree::deserialize(
+            "a;A;;And:;1\n\
+             b;B;;And:a;30\n\
+             c;C;;And:a;5\n\
+             d;D;;And:c;5\n\
+             e;E;;Or:b,d;10",
+        );
+        let unlocked = HashSet::from(["a".to_string()]);
+
+        assert_eq!(
+            tech_tree.get_path_min_hops_within_budget("e", &unlocked, 40),
+            Some(vec!["b".to_string(), "e".to_string()])
+        );
+        assert_eq!(
+            tech_tree.get_path_min_hops_within_budget("e", &unlocked, 39),
+            Some(vec!["c".to_string(), "d".to_string(), "e".to_string()])
+        );
+        assert_eq!(
+            tech_tree.get_path_min_hops_within_budget("e", &unlocked, 19),
+            None
+        );
+
+        // A wide fan-in of `And` prerequisites is researched as a whole.
+        let mut tech_tree = TechnologyTree::new();
+        let roots: Vec<String> = (0..40).map(|i| format!("root_{:02}", i)).collect();
+        for root in &roots {
+            tech_tree.add_technology(new_tech(root, &[]));
+        }
+        let roots: Vec<&str> = roots.iter().map(String::as_str).collect();
+        tech_tree.add_technology(new_tech("target", &roots));
+        let path = tech_tree
+            .get_path_min_hops_within_budget("target", &HashSet::new(), 205)
+            .unwrap();
+        assert_eq!(path.len(), 41);
+        assert_eq!(path.last().map(String::as_str), Some("target"));
+        assert_eq!(
+            tech_tree.get_path_min_hops_within_budget("target", &HashSet::new(), 204),
+            None
+        );
+    }
+
+    #[test]
+    fn test_iter_unlockable() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             education;Education;;And:writing;20",
+        );
+        let unlocked = HashSet::from(["pottery".to_string()]);
+
+        let mut ids: Vec<&str> = tech_tree
+            .iter_unlockable(&unlocked, 10)
+            .map(Technology::id)
+            .collect();
+        ids.sort_unstable();
+        assert_eq!(ids, vec!["pottery", "writing"]);
+        assert!(tech_tree.iter_unlockable(&unlocked, 4).next().is_none());
+    }
+
+    #[test]
+    fn test_iter_in_topological_order() {
+        let tech_tree = TechnologyTree::deserialize(
+            "writing;Writing;;And:pottery;10\n\
+             education;Education;;And:writing,mining;20\n\
+             pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5",
+        );
+
+        let order: Vec<&str> = tech_tree
+            .iter_in_topological_order()
+            .unwrap()
+            .map(Technology::id)
+            .collect();
+        assert_eq!(order, vec!["mining", "pottery", "writing", "education"]);
+        assert_eq!(tech_tree.topological_sort().unwrap().len(), 4);
+
+        let cyclic = TechnologyTree::deserialize("a;A;;And:b;1\nb;B;;And:a;1");
+        assert!(matches!(
+            cyclic.iter_in_topological_order(),
+            Err(TechnologyError::Cycle(_))
+        ));
+    }
+
+    #[test]
+    fn test_get_path_to_multiple_targets() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             education;Education;;And:writing;20\n\
+             philosophy;Philosophy;;And:writing;15\n\
+             mining;Mining;;And:;5",
+        );
+        let unlocked = HashSet::new();
+
+        assert_eq!(
+            tech_tree.get_path_to_multiple_targets(&["education", "philosophy"], &unlocked, 20),
+            Some(
+                ["pottery", "writing", "philosophy", "education"]
+                    .map(String::from)
+                    .to_vec()
+            )
+        );
+        assert_eq!(
+            tech_tree.get_path_to_multiple_targets(&["education", "mining"], &unlocked, 10),
+            None
+        );
+        assert_eq!(
+            tech_tree.get_path_to_multiple_targets(&[], &unlocked, 10),
+            Some(Vec::new())
+        );
+
+        let tech_tree = TechnologyTree::deserialize(
+            "a;A;;And:;4000000000\n\
+             b;B;;And:a;4280000000",
+        );
+        assert_eq!(
+            tech_tree.get_path_to_multiple_targets(&["b"], &HashSet::new(), u32::MAX),
+            Some(vec!["a".to_string(), "b".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_streaming_serialization() {
+        let tech_tree = TechnologyTree::deserialize(
+            "writing;Writing;Basics of writing.;And:pottery;10;1;science\n\
+             pottery;Pottery;Basic pottery techniques.;And:;5",
+        );
+
+        let mut buffer = Vec::new();
+        tech_tree.serialize_to_writer(&mut buffer).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer.clone()).unwrap(),
+            tech_tree.serialize()
+        );
+
+        let restored = TechnologyTree::deserialize_from_reader(io::Cursor::new(buffer)).unwrap();
+        assert_eq!(restored.serialize(), tech_tree.serialize());
+
+        let broken = "pottery;Pottery;;And:;5\n\nwriting;Writing";
+        assert!(matches!(
+            TechnologyTree::deserialize_from_reader(broken.as_bytes()),
+            Err(TechnologyError::Parse { line: 3, .. })
+        ));
     }
 
     #[test]
-    fn test_remove_technology() {
+    fn test_get_longest_chain_length() {
+        let tech_tree = TechnologyTree::deserialize(
+            "a;A;;And:;10\n\
+             b;B;;And:a;10\n\
+             c;C;;And:b;10\n\
+             d;D;;And:c;10\n\
+             e;E;;And:a;10\n\
+             f;F;;And:e;10",
+        );
+
+        assert_eq!(tech_tree.get_longest_chain_length(), 3);
+        assert_eq!(TechnologyTree::new().get_longest_chain_length(), 0);
+    }
+
+    #[test]
+    fn test_remove_technology_replace_prerequisites() {
+        let mut tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             education;Education;;And:writing;20\n\
+             philosophy;Philosophy;;Or:writing,mining;20",
+        );
+
+        tech_tree
+            .remove_technology_replace_prerequisites(
+                "writing",
+                Prerequisites::and_from_strs(&["pottery"]),
+            )
+            .unwrap();
+
+        assert!(tech_tree.get_technology("writing").is_none());
+        assert_eq!(
+            tech_tree
+                .get_technology("education")
+                .unwrap()
+                .prerequisites(),
+            &Prerequisites::and_from_strs(&["pottery"])
+        );
+        assert_eq!(
+            tech_tree
+                .get_technology("philosophy")
+                .unwrap()
+                .prerequisites(),
+            &Prerequisites::or_from_strs(&["mining", "pottery"])
+        );
+        assert_eq!(tech_tree.get_technology_version("education"), Some(1));
+        assert!(tech_tree.validate().is_ok());
+
+        assert!(matches!(
+            tech_tree.remove_technology_replace_prerequisites("writing", Prerequisites::none()),
+            Err(TechnologyError::NotFound(_))
+        ));
+
+        // Merging several Or ids into an And dependent would change what it requires.
+        let mut tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             education;Education;;And:writing,mining;20",
+        );
+        assert!(matches!(
+            tech_tree.remove_technology_replace_prerequisites(
+                "writing",
+                Prerequisites::or_from_strs(&["pottery", "mining"]),
+            ),
+            Err(TechnologyError::PrerequisiteTypeMismatch { .. })
+        ));
+        assert!(matches!(
+            tech_tree.remove_technology_replace_prerequisites(
+                "writing",
+                Prerequisites::and_from_strs(&["missing"]),
+            ),
+            Err(TechnologyError::NotFound(id)) if id == "missing"
+        ));
+        match tech_tree.remove_technology_replace_prerequisites(
+            "writing",
+            Prerequisites::and_from_strs(&["education"]),
+        ) {
+            Err(TechnologyError::Cycle(cycle)) => {
+                assert_eq!(cycle, vec!["education", "education"])
+            }
+            other => panic!("expected a cycle, got {:?}", other),
+        }
+        assert!(tech_tree.get_technology("writing").is_some());
+        assert_eq!(tech_tree.get_technology_version("education"), Some(0));
+    }
+
+    #[test]
+    fn test_get_bottleneck_depth() {
+        let tech_tree = TechnologyTree::deserialize(
+            "a;A;;And:;1\n\
+             b;B;;And:a;1\n\
+             c;C;;And:b;1\n\
+             d;D;;And:c;1\n\
+             e;E;;And:c;1\n\
+             f;F;;And:a;1",
+        );
+
+        assert_eq!(tech_tree.get_bottleneck_depth(), Some(("c".to_string(), 2)));
+        assert_eq!(TechnologyTree::new().get_bottleneck_depth(), None);
+    }
+
+    #[test]
+    fn test_get_parallel_research_depth() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             education;Education;;And:writing,mining;20",
+        );
+
+        assert_eq!(tech_tree.get_parallel_research_depth(), 3);
+        assert_eq!(TechnologyTree::new().get_parallel_research_depth(), 0);
+    }
+
+    #[test]
+    fn test_add_technology_after() {
         let mut tech_tree = TechnologyTree::new();
-        let tech_id = "pottery".to_string();
-        let tech = Technology {
-            id: tech_id.clone(),
-            name: "Pottery".to_string(),
-            description: "Basic pottery techniques.".to_string(),
-            prerequisites: Prerequisites::And(HashSet::new()),
-            cost: 5,
+        tech_tree.add_technology(new_tech("pottery", &[]));
+
+        tech_tree
+            .add_technology_after(new_tech("writing", &[]), "pottery", PrerequisiteType::Or)
+            .unwrap();
+        assert_eq!(
+            tech_tree.get_technology("writing").unwrap().prerequisites(),
+            &Prerequisites::or_from_strs(&["pottery"])
+        );
+
+        assert!(matches!(
+            tech_tree.add_technology_after(
+                new_tech("mining", &[]),
+                "bronze",
+                PrerequisiteType::And
+            ),
+            Err(TechnologyError::NotFound(_))
+        ));
+        assert!(matches!(
+            tech_tree.add_technology_after(
+                new_tech("writing", &[]),
+                "pottery",
+                PrerequisiteType::And
+            ),
+            Err(TechnologyError::DuplicateId(_))
+        ));
+        assert!(matches!(
+            tech_tree.add_technology_after(
+                Technology {
+                    id: String::new(),
+                    ..new_tech("mining", &[])
+                },
+                "pottery",
+                PrerequisiteType::And
+            ),
+            Err(TechnologyError::MissingId(name)) if name == "mining"
+        ));
+        assert!(tech_tree.unnamed.is_empty());
+    }
+
+    #[test]
+    fn test_add_technology_between() {
+        let mut tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             education;Education;;And:pottery,mining;20",
+        );
+
+        tech_tree
+            .add_technology_between(new_tech("writing", &[]), "pottery", "education")
+            .unwrap();
+        assert_eq!(
+            tech_tree.get_technology("writing").unwrap().prerequisites(),
+            &Prerequisites::and_from_strs(&["pottery"])
+        );
+        assert_eq!(
+            tech_tree
+                .get_technology("education")
+                .unwrap()
+                .prerequisites(),
+            &Prerequisites::and_from_strs(&["mining", "writing"])
+        );
+
+        assert!(matches!(
+            tech_tree.add_technology_between(new_tech("bronze", &[]), "pottery", "education"),
+            Err(TechnologyError::NotAPrerequisite { .. })
+        ));
+        assert!(tech_tree.get_technology("bronze").is_none());
+
+        assert!(matches!(
+            tech_tree.add_technology_between(
+                Technology {
+                    id: String::new(),
+                    ..new_tech("bronze", &[])
+                },
+                "mining",
+                "education"
+            ),
+            Err(TechnologyError::MissingId(name)) if name == "bronze"
+        ));
+        assert!(tech_tree.unnamed.is_empty());
+        assert_eq!(
+            tech_tree
+                .get_technology("education")
+                .unwrap()
+                .prerequisites(),
+            &Prerequisites::and_from_strs(&["mining", "writing"])
+        );
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_sample_research_costs() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery,mining;10",
+        );
+
+        let costs = tech_tree.sample_research_costs(50, 5);
+        assert_eq!(costs.len(), 3);
+        assert_eq!(costs["writing"], (20.0, 0.0));
+
+        let (mean, std_dev) = costs["pottery"];
+        assert!((5.0..=10.0).contains(&mean));
+        assert!((0.0..=2.5).contains(&std_dev));
+        assert!(tech_tree.sample_research_costs(0, 5).is_empty());
+
+        // Cheapest first, even when income allows something pricier.
+        let tech_tree = TechnologyTree::deserialize(
+            "a;A;;And:;5\n\
+             b;B;;And:;8\n\
+             c;C;;And:;20000000",
+        );
+        let costs = tech_tree.sample_research_costs(20, 10);
+        assert_eq!(costs["a"], (5.0, 0.0));
+        assert_eq!(costs["b"], (13.0, 0.0));
+        assert_eq!(costs["c"], (20000013.0, 0.0));
+    }
+
+    #[test]
+    fn test_get_diameter() {
+        let tech_tree = TechnologyTree::deserialize(
+            "a;A;;And:;1\n\
+             b;B;;And:a;1\n\
+             c;C;;And:a;1\n\
+             d;D;;And:c;1\n\
+             e;E;;And:b,d;1\n\
+             f;F;;And:e;1",
+        );
+
+        assert_eq!(tech_tree.get_diameter(), 3);
+        assert_eq!(TechnologyTree::new().get_diameter(), 0);
+    }
+
+    #[test]
+    fn test_invert_prerequisites() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             irrigation;Irrigation;;Or:pottery;10",
+        );
+
+        let inverted = tech_tree.invert_prerequisites();
+        assert_eq!(
+            inverted.get_technology("pottery").unwrap().prerequisites(),
+            &Prerequisites::and_from_strs(&["irrigation", "writing"])
+        );
+        assert_eq!(
+            inverted.get_technology("writing").unwrap().prerequisites(),
+            &Prerequisites::none()
+        );
+        assert_eq!(inverted.get_technology("writing").unwrap().cost(), 10);
+    }
+
+    #[test]
+    fn test_get_research_coverage() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             education;Education;;And:writing;20",
+        );
+
+        assert_eq!(tech_tree.get_research_coverage(0, &HashSet::new()), 0.0);
+        assert_eq!(tech_tree.get_research_coverage(10, &HashSet::new()), 0.5);
+        assert_eq!(tech_tree.get_research_coverage(40, &HashSet::new()), 1.0);
+
+        let unlocked = HashSet::from(["pottery".to_string()]);
+        assert_eq!(tech_tree.get_research_coverage(10, &unlocked), 0.5);
+    }
+
+    #[test]
+    fn test_set_technology_cost_by_predicate() {
+        let mut tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             education;Education;;And:writing;20",
+        );
+        let version = tech_tree.version();
+
+        tech_tree.set_technology_cost_by_predicate(|tech| tech.cost() >= 10, 15);
+        assert_eq!(tech_tree.get_technology("pottery").unwrap().cost(), 5);
+        assert_eq!(tech_tree.get_technology("writing").unwrap().cost(), 15);
+        assert_eq!(tech_tree.get_technology("education").unwrap().cost(), 15);
+        assert_eq!(tech_tree.get_technology_version("writing"), Some(1));
+        assert_eq!(tech_tree.version(), version + 1);
+    }
+
+    #[test]
+    fn test_get_research_priority_queue() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;10\n\
+             sailing;Sailing;;And:;15\n\
+             writing;Writing;;And:pottery;10\n\
+             education;Education;;And:writing;20\n\
+             masonry;Masonry;;And:mining;10\n\
+             bronze;Bronze;;And:mining;10",
+        );
+        let unlocked = HashSet::new();
+        let order = |strategy| -> Vec<&str> {
+            tech_tree
+                .get_research_priority_queue(&unlocked, 20, strategy)
+                .into_iter()
+                .map(Technology::id)
+                .collect()
         };
-        tech_tree.add_technology(tech);
 
-        assert!(tech_tree.remove_technology(&tech_id).is_ok());
-        assert!(!tech_tree.technologies.contains_key(&tech_id));
+        assert_eq!(
+            order(ResearchStrategy::CheapestFirst),
+            vec!["pottery", "mining", "sailing"]
+        );
+        assert_eq!(
+            order(ResearchStrategy::HighestCostFirst),
+            vec!["sailing", "mining", "pottery"]
+        );
+        assert_eq!(
+            order(ResearchStrategy::MostDependentsFirst),
+            vec!["mining", "pottery", "sailing"]
+        );
+        assert_eq!(
+            order(ResearchStrategy::ShortestPathToLeaf),
+            vec!["sailing", "mining", "pottery"]
+        );
     }
 
     #[test]
-    fn test_remove_technology_with_dependency() {
+    fn test_prerequisites_satisfied_by() {
+        let unlocked = HashSet::from(["pottery".to_string()]);
+        let writing = new_tech("writing", &["pottery"]).with_cost(1000);
+        let education = new_tech("education", &["pottery", "writing"]);
+        let philosophy = Technology::new(
+            "philosophy".to_string(),
+            "Philosophy".to_string(),
+            String::new(),
+            Prerequisites::or_from_strs(&["pottery", "writing"]),
+            10,
+        );
+
+        assert!(writing.prerequisites_satisfied_by(&unlocked));
+        assert!(!education.prerequisites_satisfied_by(&unlocked));
+        assert!(philosophy.prerequisites_satisfied_by(&unlocked));
+        assert!(new_tech("pottery", &[]).prerequisites_satisfied_by(&HashSet::new()));
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_get_random_valid_research_order() {
+        use rand::SeedableRng;
+
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             sailing;Sailing;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             education;Education;;And:writing,mining;20\n\
+             philosophy;Philosophy;;Or:education,sailing;20",
+        );
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        for _ in 0..20 {
+            let order = tech_tree.get_random_valid_research_order(&mut rng).unwrap();
+            assert_eq!(order.len(), 6);
+
+            let mut researched = HashSet::new();
+            for tech_id in order {
+                assert!(tech_tree.is_unlockable(&tech_id, &researched, u32::MAX));
+                researched.insert(tech_id);
+            }
+        }
+
+        let stuck = TechnologyTree::deserialize("pottery;Pottery;;Or:;5");
+        assert!(matches!(
+            stuck.get_random_valid_research_order(&mut rng),
+            Err(TechnologyError::Unreachable(_))
+        ));
+    }
+
+    #[test]
+    fn test_get_prerequisite_closure_for_set() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             masonry;Masonry;;And:mining;10\n\
+             education;Education;;And:writing;20",
+        );
+
+        let targets = HashSet::from(["education".to_string(), "masonry".to_string()]);
+        assert_eq!(
+            tech_tree.get_prerequisite_closure_for_set(&targets),
+            HashSet::from(["pottery", "writing", "mining"].map(String::from))
+        );
+
+        let targets = HashSet::from(["education".to_string(), "writing".to_string()]);
+        assert_eq!(
+            tech_tree.get_prerequisite_closure_for_set(&targets),
+            HashSet::from(["pottery".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_get_technologies_by_prerequisite_count() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             education;Education;;And:pottery,mining;20\n\
+             writing;Writing;;Or:pottery;10",
+        );
+
+        let counts: Vec<(&str, usize)> = tech_tree
+            .get_technologies_by_prerequisite_count()
+            .into_iter()
+            .map(|(tech, count)| (tech.id(), count))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                ("mining", 0),
+                ("pottery", 0),
+                ("writing", 1),
+                ("education", 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fan_out() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             irrigation;Irrigation;;And:pottery;10\n\
+             education;Education;;And:writing;20",
+        );
+
+        let (tech, fan_out) = tech_tree.get_max_fan_out().unwrap();
+        assert_eq!((tech.id(), fan_out), ("pottery", 2));
+        assert_eq!(tech_tree.get_avg_fan_out(), 0.75);
+        assert!(TechnologyTree::new().get_max_fan_out().is_none());
+    }
+
+    #[test]
+    fn test_fan_in() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             education;Education;;And:writing,mining;20",
+        );
+
+        let (tech, fan_in) = tech_tree.get_max_fan_in().unwrap();
+        assert_eq!((tech.id(), fan_in), ("education", 2));
+        assert_eq!(tech_tree.get_avg_fan_in(), 0.75);
+        assert_eq!(TechnologyTree::new().get_avg_fan_in(), 0.0);
+    }
+
+    #[test]
+    fn test_get_technology_path_bfs() {
+        let tech_tree = TechnologyTree::deserialize(
+            "a;A;;And:;1\n\
+             b;B;;And:a;1000\n\
+             c;C;;And:a;1\n\
+             d;D;;And:c;1\n\
+             e;E;;Or:b,d;1",
+        );
+        let unlocked = HashSet::from(["a".to_string()]);
+
+        assert_eq!(
+            tech_tree.get_technology_path_bfs("e", &unlocked),
+            Some(vec!["b".to_string(), "e".to_string()])
+        );
+        assert_eq!(
+            tech_tree.get_technology_path_bfs("a", &unlocked),
+            Some(Vec::new())
+        );
+        assert_eq!(
+            tech_tree.get_technology_path_bfs("missing", &unlocked),
+            None
+        );
+
+        // Many independent prerequisites used to blow up the search.
         let mut tech_tree = TechnologyTree::new();
-        let mut prereq = HashSet::new();
-        prereq.insert("pottery".to_string());
+        let roots: Vec<String> = (0..40).map(|i| format!("root_{:02}", i)).collect();
+        for root in &roots {
+            tech_tree.add_technology(new_tech(root, &[]));
+        }
+        let roots: Vec<&str> = roots.iter().map(String::as_str).collect();
+        tech_tree.add_technology(new_tech("target", &roots));
+        let path = tech_tree
+            .get_technology_path_bfs("target", &HashSet::new())
+            .unwrap();
+        assert_eq!(path.len(), 41);
+        assert_eq!(path.last().map(String::as_str), Some("target"));
+    }
 
-        let tech1 = Technology {
-            id: "pottery".to_string(),
-            name: "Pottery".to_string(),
-            description: "Basic pottery techniques.".to_string(),
-            prerequisites: Prerequisites::And(HashSet::new()),
-            cost: 5,
-        };
+    #[test]
+    fn test_compact_serialization() {
+        // Distinct names and descriptions, built from a fixed vocabulary like real content.
+        let words: Vec<&str> = "ancient advanced bronze iron steam electric naval military \
+                                agriculture trade writing philosophy engineering astronomy \
+                                medicine banking printing gunpowder railroad industry refining \
+                                flight radio computers rocketry masonry sailing mathematics \
+                                theology chivalry economics chemistry biology physics ballistics"
+            .split_whitespace()
+            .collect();
+        let phrases = [
+            "Allows your cities to build",
+            "Improves the yield of",
+            "Grants access to",
+            "Increases the production of",
+            "Reduces the cost of maintaining",
+            "Unlocks new units specialised in",
+        ];
+        let mut next = lcg(7);
 
-        let tech2 = Technology {
-            id: "irrigation".to_string(),
-            name: "Irrigation".to_string(),
-            description: "Advanced irrigation techniques.".to_string(),
-            prerequisites: Prerequisites::And(prereq.clone()),
-            cost: 10,
+        let mut data = String::new();
+        for i in 0..200 {
+            let name = format!(
+                "{} {} {}",
+                words[next(words.len())],
+                words[next(words.len())],
+                i
+            );
+            let description = format!(
+                "{} {} {} and {}. Required for {} {}.",
+                phrases[next(phrases.len())],
+                words[next(words.len())],
+                words[next(words.len())],
+                words[next(words.len())],
+                words[next(words.len())],
+                words[next(words.len())]
+            );
+            let prereqs = match i {
+                0 => String::new(),
+                _ => format!("technology_{:03}", next(i)),
+            };
+            data.push_str(&format!(
+                "technology_{:03};{};{};And:{};{};{}\n",
+                i,
+                name,
+                description,
+                prereqs,
+                10 + next(500),
+                i / 50
+            ));
+        }
+        let tech_tree = TechnologyTree::deserialize(&data);
+
+        let compact = tech_tree.compact_serialize();
+        let restored = TechnologyTree::compact_deserialize(&compact).unwrap();
+        assert_eq!(restored.serialize(), tech_tree.serialize());
+        assert!(compact.len() * 10 <= tech_tree.serialize().len() * 6);
+
+        assert!(matches!(
+            TechnologyTree::compact_deserialize(&compact[..compact.len() - 1]),
+            Err(TechnologyError::Io(_))
+        ));
+
+        // Announces 2^36 bytes: one literal and a single match copying it over and over.
+        let crafted = [
+            0x80, 0x80, 0x80, 0x80, 0x80, 0x02, 0x02, 0x61, 0xf7, 0xff, 0xff, 0xff, 0xff, 0x03,
+            0x01,
+        ];
+        match TechnologyTree::compact_deserialize(&crafted) {
+            Err(TechnologyError::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::InvalidData),
+            other => panic!("expected invalid data, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_get_research_rank() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;30\n\
+             writing;Writing;;And:pottery;10\n\
+             education;Education;;And:writing;20",
+        );
+
+        assert_eq!(tech_tree.get_research_rank_by_cost("pottery"), Some(1));
+        assert_eq!(tech_tree.get_research_rank_by_cost("mining"), Some(4));
+        assert_eq!(tech_tree.get_research_rank_by_depth("mining"), Some(1));
+        assert_eq!(tech_tree.get_research_rank_by_depth("education"), Some(4));
+        assert_eq!(tech_tree.get_research_rank_by_cost("missing"), None);
+    }
+
+    #[test]
+    fn test_get_related_technologies() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             irrigation;Irrigation;;Or:pottery,mining;10\n\
+             masonry;Masonry;;And:mining;10",
+        );
+
+        let related: Vec<&str> = tech_tree
+            .get_related_technologies("writing")
+            .into_iter()
+            .map(Technology::id)
+            .collect();
+        assert_eq!(related, vec!["irrigation"]);
+        assert_eq!(tech_tree.get_related_technologies("irrigation").len(), 2);
+        assert!(tech_tree.get_related_technologies("pottery").is_empty());
+    }
+
+    #[test]
+    fn test_get_cost_profile() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             education;Education;;And:writing;20",
+        );
+        let sequence = ["pottery", "writing", "education"].map(String::from);
+
+        assert_eq!(
+            tech_tree.get_cost_profile(&sequence),
+            vec![(1, 5), (2, 15), (3, 35)]
+        );
+        assert!(tech_tree.get_cost_profile(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_get_unlock_wave() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             education;Education;;And:writing,mining;20\n\
+             philosophy;Philosophy;;Or:education,mining;20\n\
+             alchemy;Alchemy;;And:missing;20",
+        );
+
+        assert_eq!(tech_tree.get_unlock_wave("pottery"), Some(0));
+        assert_eq!(tech_tree.get_unlock_wave("education"), Some(2));
+        assert_eq!(tech_tree.get_unlock_wave("philosophy"), Some(1));
+        assert_eq!(tech_tree.get_unlock_wave("alchemy"), None);
+        assert_eq!(tech_tree.get_unlock_wave("missing"), None);
+    }
+
+    #[test]
+    fn test_group_by_wave() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             masonry;Masonry;;And:mining;10\n\
+             education;Education;;And:writing,mining;20",
+        );
+
+        let waves: Vec<Vec<&str>> = tech_tree
+            .group_by_wave()
+            .into_iter()
+            .map(|wave| wave.into_iter().map(Technology::name).collect())
+            .collect();
+        assert_eq!(
+            waves,
+            vec![
+                vec!["Mining", "Pottery"],
+                vec!["Masonry", "Writing"],
+                vec!["Education"]
+            ]
+        );
+        assert!(TechnologyTree::new().group_by_wave().is_empty());
+    }
+
+    #[test]
+    fn test_get_completion_percentage() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             education;Education;;And:writing;20",
+        );
+        let unlocked = HashSet::from(["pottery".to_string(), "writing".to_string()]);
+
+        assert_eq!(tech_tree.get_completion_percentage(&unlocked), 50.0);
+        assert_eq!(tech_tree.get_completion_percentage_by_cost(&unlocked), 37.5);
+        assert_eq!(tech_tree.get_completion_percentage(&HashSet::new()), 0.0);
+        assert_eq!(
+            TechnologyTree::new().get_completion_percentage_by_cost(&unlocked),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_get_technologies_in_prerequisite_order() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             education;Education;;And:writing;20\n\
+             mining;Mining;;And:;5",
+        );
+
+        let order: Vec<&str> = tech_tree
+            .get_technologies_in_prerequisite_order("education", &HashSet::new())
+            .unwrap()
+            .into_iter()
+            .map(Technology::name)
+            .collect();
+        assert_eq!(order, vec!["Pottery", "Writing", "Education"]);
+        assert!(tech_tree
+            .get_technologies_in_prerequisite_order("missing", &HashSet::new())
+            .is_none());
+    }
+
+    #[test]
+    fn test_simulate_research_schedule() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             education;Education;;And:writing;20",
+        );
+
+        let schedule = tech_tree.simulate_research_schedule(HashSet::new(), 10);
+        assert_eq!(
+            schedule,
+            vec![
+                (1, "mining".to_string()),
+                (1, "pottery".to_string()),
+                (2, "writing".to_string()),
+                (4, "education".to_string()),
+            ]
+        );
+        assert!(tech_tree
+            .simulate_research_schedule(HashSet::new(), 0)
+            .is_empty());
+
+        let tech_tree = TechnologyTree::deserialize(
+            "a;A;;And:;20000000\n\
+             b;B;;And:a;4280000000",
+        );
+        assert_eq!(
+            tech_tree.simulate_research_schedule(HashSet::new(), 1),
+            vec![(20_000_000, "a".to_string())]
+        );
+        assert_eq!(
+            tech_tree.simulate_research_schedule(HashSet::new(), 2),
+            vec![
+                (10_000_000, "a".to_string()),
+                (2_150_000_000, "b".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_newly_unlockable_after_unlock() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             education;Education;;And:writing,mining;20\n\
+             irrigation;Irrigation;;Or:pottery,mining;10\n\
+             masonry;Masonry;;And:mining;50",
+        );
+        let previous = HashSet::from(["pottery".to_string(), "writing".to_string()]);
+
+        let ids: Vec<&str> = tech_tree
+            .get_newly_unlockable_after_unlock("mining", &previous, 20)
+            .into_iter()
+            .map(Technology::id)
+            .collect();
+        assert_eq!(ids, vec!["education"]);
+    }
+
+    #[test]
+    fn test_get_technologies_locked_by_unlock() {
+        let mut tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             writing;Writing;;Or:pottery,mining;10;1",
+        );
+        tech_tree.set_era_threshold(1, 1);
+        let previous = HashSet::from(["pottery".to_string()]);
+
+        assert!(tech_tree
+            .get_technologies_locked_by_unlock("mining", &previous)
+            .is_empty());
+
+        let tech_tree: TechnologyTree = "monarchy;Monarchy;;And:;5\n\
+                                         republic;Republic;;And:;5;0;;monarchy\n\
+                                         senate;Senate;;And:republic;10;0;;monarchy"
+            .parse()
+            .unwrap();
+        let ids = |technologies: Vec<&Technology>| -> Vec<String> {
+            technologies.iter().map(|tech| tech.id.clone()).collect()
         };
+        assert_eq!(
+            ids(tech_tree.get_technologies_locked_by_unlock("monarchy", &HashSet::new())),
+            vec!["republic"]
+        );
+        let previous = HashSet::from(["republic".to_string()]);
+        assert_eq!(
+            ids(tech_tree.get_technologies_locked_by_unlock("monarchy", &previous)),
+            vec!["senate"]
+        );
+        assert!(!tech_tree.is_unlockable("republic", &HashSet::from(["monarchy".to_string()]), 10));
+        assert_eq!(
+            tech_tree
+                .to_string()
+                .parse::<TechnologyTree>()
+                .unwrap()
+                .technologies["senate"]
+                .excludes(),
+            &HashSet::from(["monarchy".to_string()])
+        );
+        assert_eq!(
+            tech_tree.to_string().len(),
+            tech_tree.estimate_serialized_size()
+        );
+        let restored = TechnologyTree::compact_deserialize(&tech_tree.compact_serialize()).unwrap();
+        assert_eq!(restored.serialize(), tech_tree.serialize());
+    }
 
-        tech_tree.add_technology(tech1);
-        tech_tree.add_technology(tech2);
+    #[test]
+    fn test_get_balance_score() {
+        let chain = TechnologyTree::deserialize(
+            "a;A;;And:;10\n\
+             b;B;;And:a;10\n\
+             c;C;;And:b;10\n\
+             d;D;;And:c;10",
+        );
+        let branching = TechnologyTree::deserialize(
+            "a;A;;And:;10\n\
+             b;B;;And:;10\n\
+             c;C;;And:a;10\n\
+             d;D;;And:b;10\n\
+             e;E;;Or:a,b;10\n\
+             f;F;;And:c,d;10",
+        );
 
-        assert!(tech_tree.remove_technology("pottery").is_err());
+        let chain_score = chain.get_balance_score();
+        let branching_score = branching.get_balance_score();
+        assert!((0.0..=100.0).contains(&chain_score));
+        assert!((0.0..=100.0).contains(&branching_score));
+        assert!(branching_score > chain_score);
+        assert_eq!(TechnologyTree::new().get_balance_score(), 0.0);
     }
 
     #[test]
-    fn test_unlock_technology() {
-        let mut tech_tree = TechnologyTree::new();
-        let mut unlocked = HashSet::new();
-        unlocked.insert("pottery".to_string());
+    fn test_from_dot_string() {
+        let dot = r#"digraph tech_tree {
+            // Ancient era
+            node [shape=box];
+            pottery [label="Pottery", cost=5];
+            writing [label="Writing" cost="10"];
+            pottery -> writing -> education;
+            mining -> education [color=red];
+        }"#;
 
-        let tech = Technology {
-            id: "writing".to_string(),
-            name: "Writing".to_string(),
-            description: "Basics of writing.".to_string(),
-            prerequisites: Prerequisites::And(unlocked.clone()),
-            cost: 10,
+        let tech_tree = TechnologyTree::from_dot_string(dot).unwrap();
+        assert_eq!(
+            tech_tree.get_technology_ids(),
+            vec!["education", "mining", "pottery", "writing"]
+        );
+        assert_eq!(tech_tree.get_technology("writing").unwrap().cost(), 10);
+        assert_eq!(
+            tech_tree.get_technology("pottery").unwrap().name(),
+            "Pottery"
+        );
+        let education = tech_tree.get_technology("education").unwrap();
+        assert_eq!((education.name(), education.cost()), ("education", 1));
+        assert_eq!(
+            education.prerequisites(),
+            &Prerequisites::and_from_strs(&["mining", "writing"])
+        );
+
+        assert!(matches!(
+            TechnologyTree::from_dot_string("digraph {\n  a [cost=cheap];\n}"),
+            Err(TechnologyError::Parse { line: 2, .. })
+        ));
+        assert!(matches!(
+            TechnologyTree::from_dot_string("graph { a -- b }"),
+            Err(TechnologyError::Parse { line: 1, .. })
+        ));
+
+        // Node defaults apply to the nodes created after them, explicit attributes win.
+        let tech_tree = TechnologyTree::from_dot_string(
+            "digraph {
+                early;
+                node [cost=20];
+                late;
+                cheap [cost=3];
+                late -> edge_node;
+            }",
+        )
+        .unwrap();
+        let cost = |id: &str| tech_tree.get_technology(id).unwrap().cost();
+        assert_eq!(
+            (
+                cost("early"),
+                cost("late"),
+                cost("cheap"),
+                cost("edge_node")
+            ),
+            (1, 20, 3, 20)
+        );
+    }
+
+    #[test]
+    fn test_get_research_path_narrative() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             writing;Writing;;And:pottery;10\n\
+             bronze;Bronze Working;;And:writing;15\n\
+             iron;Iron Working;;And:bronze;20",
+        );
+
+        assert_eq!(
+            tech_tree
+                .get_research_path_narrative("iron", &HashSet::new(), 20)
+                .unwrap(),
+            "To unlock Iron Working, you must first research Pottery (5 pts), then Writing \
+             (10 pts), then Bronze Working (15 pts), and finally Iron Working (20 pts) for a \
+             total of 50 science points."
+        );
+
+        let unlocked = HashSet::from(["pottery".to_string()]);
+        assert_eq!(
+            tech_tree
+                .get_research_path_narrative("writing", &unlocked, 20)
+                .unwrap(),
+            "To unlock Writing, research Writing (10 pts) for a total of 10 science points."
+        );
+        assert_eq!(
+            tech_tree
+                .get_research_path_narrative("pottery", &unlocked, 20)
+                .unwrap(),
+            "Pottery has already been researched."
+        );
+        assert_eq!(
+            tech_tree.get_research_path_narrative("iron", &unlocked, 10),
+            None
+        );
+    }
+
+    #[test]
+    fn test_content_audit() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;Basic pottery techniques.;And:;5\n\
+             writing;Writing; ;And:pottery;10\n\
+             placeholder;;;And:pottery;10",
+        );
+
+        let ids = |technologies: Vec<&Technology>| -> Vec<String> {
+            technologies
+                .iter()
+                .map(|tech| tech.id().to_string())
+                .collect()
         };
+        assert_eq!(
+            ids(tech_tree.get_technologies_with_empty_description()),
+            vec!["placeholder", "writing"]
+        );
+        assert_eq!(
+            ids(tech_tree.get_technologies_with_empty_name()),
+            vec!["placeholder"]
+        );
+        assert!(tech_tree
+            .describe()
+            .contains("- Missing names: placeholder\n"));
+    }
 
-        tech_tree.add_technology(tech.clone());
+    #[test]
+    fn test_estimate_serialized_size() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;Basic pottery techniques.;And:;5\n\
+             mining;Mining;;And:;0\n\
+             writing;Writing;Basics of writing.;Or:pottery,mining;10;1\n\
+             education;Education;;And:writing;1200;2;science,culture",
+        );
 
-        assert!(tech_tree.unlock_technology(&tech.id, &mut unlocked, 15));
+        assert_eq!(
+            tech_tree.estimate_serialized_size(),
+            tech_tree.serialize().len()
+        );
+        assert_eq!(TechnologyTree::new().estimate_serialized_size(), 0);
+    }
+
+    #[test]
+    fn test_get_recommended_technology() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             hunting;Hunting;;And:;5\n\
+             bronze;Bronze;;And:mining;10\n\
+             iron;Iron;;And:bronze;20\n\
+             writing;Writing;;And:pottery;10\n\
+             archery;Archery;;And:hunting;10",
+        );
+        let unlocked = HashSet::new();
+
+        let recommended = tech_tree.get_recommended_technology(&unlocked, 100, None);
+        assert_eq!(recommended.map(|tech| tech.id.as_str()), Some("mining"));
+
+        let recommended = tech_tree.get_recommended_technology(&unlocked, 100, Some("writing"));
+        assert_eq!(recommended.map(|tech| tech.id.as_str()), Some("pottery"));
+
+        let unlocked = HashSet::from(["pottery".to_string()]);
+        let recommended = tech_tree.get_recommended_technology(&unlocked, 100, None);
+        assert_eq!(recommended.map(|tech| tech.id.as_str()), Some("mining"));
+        assert!(tech_tree
+            .get_recommended_technology(&unlocked, 100, Some("pottery"))
+            .is_none());
+        assert!(tech_tree
+            .get_recommended_technology(&unlocked, 1, None)
+            .is_none());
+    }
+
+    #[test]
+    fn test_serialize_is_idempotent() {
+        let mut next = lcg(0x5eed);
+
+        for size in 1..=20 {
+            let mut lines: Vec<String> = (0..size)
+                .map(|index| {
+                    let prerequisites: Vec<String> = (0..index)
+                        .filter(|_| next(3) == 0)
+                        .map(|prerequisite| format!("tech{}", prerequisite))
+                        .collect();
+                    let kind = if next(2) == 0 { "And" } else { "Or" };
+                    let mut line = format!(
+                        "tech{};Tech {};;{}:{};{}",
+                        index,
+                        index,
+                        kind,
+                        prerequisites.join(","),
+                        next(500)
+                    );
+                    if next(2) == 0 {
+                        line.push_str(&format!(";{};tag{},tag{}", next(4), next(3), next(3)));
+                    }
+                    line
+                })
+                .collect();
+            for index in (1..lines.len()).rev() {
+                lines.swap(index, next(index + 1));
+            }
+
+            let tech_tree = TechnologyTree::deserialize(&lines.join("\n"));
+            let serialized = tech_tree.serialize();
+            assert_eq!(serialized, tech_tree.serialize());
+            assert_eq!(
+                TechnologyTree::deserialize(&serialized).serialize(),
+                serialized
+            );
+
+            let ids: Vec<&str> = serialized
+                .lines()
+                .map(|line| line.split(';').next().unwrap())
+                .collect();
+            assert_eq!(ids, tech_tree.get_tech_ids_in_stable_order());
+        }
+    }
+
+    #[test]
+    fn test_get_transitive_and_prerequisites() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             hunting;Hunting;;And:;5\n\
+             bronze;Bronze;;And:mining;10\n\
+             writing;Writing;;And:pottery;10\n\
+             archery;Archery;;Or:hunting,bronze;10\n\
+             currency;Currency;;And:writing,archery;20",
+        );
+
+        let expected: HashSet<String> = ["pottery", "writing", "archery"]
+            .iter()
+            .map(|id| id.to_string())
+            .collect();
+        assert_eq!(
+            tech_tree
+                .get_transitive_and_prerequisites("currency")
+                .unwrap(),
+            expected
+        );
+        assert!(tech_tree
+            .get_transitive_and_prerequisites("pottery")
+            .unwrap()
+            .is_empty());
+        assert!(matches!(
+            tech_tree.get_transitive_and_prerequisites("iron"),
+            Err(TechnologyError::NotFound(_))
+        ));
+
+        let mut cyclic = TechnologyTree::new();
+        cyclic
+            .technologies
+            .insert("a".to_string(), new_tech("a", &["b"]));
+        cyclic
+            .technologies
+            .insert("b".to_string(), new_tech("b", &["a"]));
+        cyclic
+            .technologies
+            .insert("c".to_string(), new_tech("c", &["a"]));
+        assert!(matches!(
+            cyclic.get_transitive_and_prerequisites("c"),
+            Err(TechnologyError::Cycle(_))
+        ));
+    }
+
+    #[test]
+    fn test_get_transitive_or_prerequisites() {
+        let tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;5\n\
+             hunting;Hunting;;And:;5\n\
+             bronze;Bronze;;And:mining;10\n\
+             writing;Writing;;Or:pottery,mining;10\n\
+             archery;Archery;;Or:hunting,bronze;10\n\
+             currency;Currency;;And:writing,archery;20",
+        );
+
+        let sets: Vec<Vec<String>> = tech_tree
+            .get_transitive_or_prerequisites("currency")
+            .into_iter()
+            .map(|set| {
+                let mut ids: Vec<String> = set.into_iter().collect();
+                ids.sort_unstable();
+                ids
+            })
+            .collect();
+        assert_eq!(
+            sets,
+            vec![
+                vec!["archery", "bronze", "mining", "writing"],
+                vec!["archery", "hunting", "mining", "writing"],
+                vec!["archery", "hunting", "pottery", "writing"],
+            ]
+        );
+
+        assert_eq!(
+            tech_tree.get_transitive_or_prerequisites("pottery"),
+            vec![HashSet::new()]
+        );
+        assert!(tech_tree.get_transitive_or_prerequisites("iron").is_empty());
+    }
+
+    #[test]
+    fn test_auto_assign_costs() {
+        let mut tech_tree = TechnologyTree::deserialize(
+            "pottery;Pottery;;And:;5\n\
+             mining;Mining;;And:;7\n\
+             bronze;Bronze;;And:mining;10\n\
+             writing;Writing;;Or:pottery,bronze;10",
+        );
+        let version = tech_tree.version();
+
+        tech_tree.auto_assign_costs(10, 1.5);
+
+        let cost =
+            |tech_tree: &TechnologyTree, id: &str| tech_tree.get_technology(id).unwrap().cost;
+        assert_eq!(cost(&tech_tree, "pottery"), 10);
+        assert_eq!(cost(&tech_tree, "mining"), 10);
+        assert_eq!(cost(&tech_tree, "bronze"), 15);
+        assert_eq!(cost(&tech_tree, "writing"), 23);
+        assert_eq!(tech_tree.version(), version + 1);
+        assert_eq!(tech_tree.get_technology_version("bronze"), Some(1));
+        assert_eq!(tech_tree.get_technology_version("pottery"), Some(1));
+
+        tech_tree.auto_assign_costs(10, 1.5);
+        assert_eq!(tech_tree.version(), version + 1);
+    }
+
+    #[test]
+    fn test_deserialize_reads_invalid_cost_as_zero() {
+        let data = "pottery;Pottery;;And:;cheap\nmining;Mining;;And:;5;early";
+
+        let tech_tree = TechnologyTree::deserialize(data);
+        assert_eq!(tech_tree.get_technology("pottery").unwrap().cost, 0);
+        assert_eq!(tech_tree.get_technology("mining").unwrap().era, 0);
+
+        match TechnologyTree::try_from(data) {
+            Err(TechnologyError::Parse { line, message }) => {
+                assert_eq!(line, 1);
+                assert_eq!(message, "invalid cost 'cheap'");
+            }
+            _ => panic!("expected a parse error"),
+        }
     }
 }